@@ -0,0 +1,351 @@
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::gui_plugin::{GameState, GameStateMarker};
+use crate::mechanics::dialog::{DialogCamera, DialogShot};
+use crate::player_plugin::{
+    Player, PlayerModel, PlayerMovement, PlayerPhysics, CROUCHING_HEIGHT, PLAYER_RADIUS, STANDING_HEIGHT,
+};
+use crate::settings_plugin::{CameraMode, SaveSettings, Settings};
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_camera)
+            .add_systems(Update, (
+                toggle_camera_mode,
+                // movement steers by this frame's look, a fixed order also keeps replays deterministic
+                camera_control.before(PlayerMovement),
+                camera_zoom,
+                camera_follow,
+                camera_collision,
+                dialog_camera,
+            ).chain());
+    }
+}
+
+// where the camera sits relative to the pivot when nothing is in the way, scaled by zoom
+const CAMERA_OFFSET: Vec3 = Vec3::new(0.0, 2.0, 10.0);
+const MIN_DISTANCE: f32 = 2.5;
+const MAX_DISTANCE: f32 = 20.0;
+// first person eye sits this far below the top of the capsule segment
+const EYE_OFFSET: f32 = 0.1;
+// third person pivot above the player's origin while standing, crouching lowers it by the lost height
+const PIVOT_HEIGHT: f32 = 1.0;
+
+fn spawn_camera(mut commands: Commands) {
+    commands
+        .spawn((CameraOrbit::default(), Transform::default()))
+        .with_children(|parent| {
+            parent
+                .spawn(Camera3d { ..default() })
+                .insert(Transform::from_translation(CAMERA_OFFSET));
+        });
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct CameraOrbit {
+    pub pitch: f32,
+    pub yaw: f32,
+    pub target_pitch: f32, // raw mouse look, pitch/yaw follow these based on the smoothing setting
+    pub target_yaw: f32,
+    pub height: f32, // pivot offset above the player's feet, eased towards the stance height
+    pub distance: f32, // zoomed length of the camera offset
+    pub boom: f32, // fraction of the zoomed offset currently in use, shortened when something blocks the view
+    pub first_person: f32, // 0.0 is fully third person, 1.0 fully first person, eased between on toggle
+    pub dialog: f32, // blend towards the dialog framing shot, 1.0 while a conversation is up
+}
+
+impl Default for CameraOrbit {
+    fn default() -> Self {
+        Self {
+            pitch: 0.0,
+            yaw: 0.0,
+            target_pitch: 0.0,
+            target_yaw: 0.0,
+            height: PIVOT_HEIGHT,
+            distance: CAMERA_OFFSET.length(),
+            boom: 1.0,
+            first_person: 0.0,
+            dialog: 0.0,
+        }
+    }
+}
+
+impl CameraOrbit {
+    // camera offset from the pivot at the current zoom, before collision
+    pub fn offset(&self) -> Vec3 {
+        CAMERA_OFFSET.normalize() * self.distance
+    }
+}
+
+// marker for colliders the camera should pass through (foliage, thin props, glass)
+#[derive(Component)]
+pub struct CameraTransparent;
+
+fn camera_control(
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut query: Query<(&mut Transform, &mut CameraOrbit)>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    // dialog owns the camera while it's up, leave the orbit where the player left it
+    if let Ok(game_state) = game_query.get_single() {
+        if !game_state.in_gameplay() { return; }
+    }
+
+    // radians per pixel of mouse motion at sensitivity 1.0, mouse deltas are already per-frame
+    const ROTATION_SPEED: f32 = 0.005;
+    const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.1;
+
+    if let Ok((mut transform, mut orbit)) = query.get_single_mut() {
+        let mut rotation = Vec2::ZERO;
+        for event in mouse_motion.read() {
+            rotation += event.delta * ROTATION_SPEED * settings.camera.sensitivity;
+        }
+        if settings.camera.invert_y {
+            rotation.y = -rotation.y;
+        }
+
+        // (left/right)
+        orbit.target_yaw -= rotation.x;
+
+        // (up/down)
+        orbit.target_pitch = (orbit.target_pitch - rotation.y).clamp(-MAX_PITCH, MAX_PITCH);
+
+        // smoothing is framerate independent, tuned so the factor means "kept per 60hz frame"
+        let smoothing = settings.camera.smoothing.clamp(0.0, 0.95);
+        let t = 1.0 - smoothing.powf(time.delta_secs() * 60.0);
+        orbit.yaw = orbit.yaw.lerp(orbit.target_yaw, t);
+        orbit.pitch = orbit.pitch.lerp(orbit.target_pitch, t);
+
+        transform.rotation =
+            Quat::from_axis_angle(Vec3::Y, orbit.yaw) * Quat::from_axis_angle(Vec3::X, orbit.pitch);
+    }
+}
+
+fn toggle_camera_mode(
+    input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut save_writer: EventWriter<SaveSettings>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+) {
+    if let Ok(game_state) = game_query.get_single() {
+        if !game_state.in_gameplay() { return; }
+    }
+
+    if input.just_pressed(KeyCode::KeyV) {
+        settings.camera.mode = match settings.camera.mode {
+            CameraMode::ThirdPerson => CameraMode::FirstPerson,
+            CameraMode::FirstPerson => CameraMode::ThirdPerson,
+        };
+        save_writer.send(SaveSettings);
+    }
+}
+
+fn camera_zoom(
+    mut wheel: EventReader<MouseWheel>,
+    mut query: Query<&mut CameraOrbit>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+) {
+    const ZOOM_STEP: f32 = 1.0;
+    const PIXELS_PER_LINE: f32 = 100.0;
+
+    if let Ok(game_state) = game_query.get_single() {
+        if game_state.paused { return; }
+    }
+
+    let Ok(mut orbit) = query.get_single_mut() else {
+        return;
+    };
+    if orbit.first_person > 0.5 {
+        wheel.clear();
+        return;
+    }
+    for event in wheel.read() {
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        };
+        orbit.distance = (orbit.distance - lines * ZOOM_STEP).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+}
+
+fn camera_follow(
+    player_query: Query<(&Transform, &PlayerPhysics), (With<Player>, Without<CameraOrbit>)>,
+    mut camera_query: Query<(&mut Transform, &mut CameraOrbit)>,
+    mut model_query: Query<&mut Visibility, With<PlayerModel>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    const HEIGHT_SMOOTHING: f32 = 10.0;
+    const MODE_BLEND: f32 = 8.0;
+
+    if let (Ok((player_transform, physics)), Ok((mut camera_transform, mut orbit))) =
+        (player_query.get_single(), camera_query.get_single_mut())
+    {
+        let target_mode = match settings.camera.mode {
+            CameraMode::ThirdPerson => 0.0,
+            CameraMode::FirstPerson => 1.0,
+        };
+        let t = 1.0 - (-MODE_BLEND * time.delta_secs()).exp();
+        orbit.first_person = orbit.first_person.lerp(target_mode, t);
+
+        // never below the bottom sphere of the capsule, or the collision sweep starts inside the floor
+        let stance_height = if physics.crouching { CROUCHING_HEIGHT } else { STANDING_HEIGHT };
+        let third_person_height = (PIVOT_HEIGHT - (STANDING_HEIGHT - stance_height)).max(PLAYER_RADIUS);
+        let eye_height = stance_height - EYE_OFFSET;
+        let target_height = third_person_height.lerp(eye_height, orbit.first_person);
+
+        let t = 1.0 - (-HEIGHT_SMOOTHING * time.delta_secs()).exp();
+        orbit.height = orbit.height.lerp(target_height, t);
+
+        camera_transform.translation = player_transform.translation + Vec3::Y * orbit.height;
+
+        // hide our own body once the camera is close enough to be inside it
+        if let Ok(mut visibility) = model_query.get_single_mut() {
+            let hidden = orbit.first_person > 0.5 && orbit.dialog < 0.5;
+            visibility.set_if_neq(if hidden { Visibility::Hidden } else { Visibility::Inherited });
+        }
+    }
+}
+
+fn camera_collision(
+    mut pivot_query: Query<(&Transform, &mut CameraOrbit, &Children)>,
+    mut camera_query: Query<&mut Transform, (With<Camera3d>, Without<CameraOrbit>)>,
+    transparent_query: Query<(), Or<(With<CameraTransparent>, With<Player>)>>,
+    rapier_context: Query<&RapierContext>,
+    time: Res<Time>,
+) {
+    const CAMERA_RADIUS: f32 = 0.3;
+    const MIN_BOOM: f32 = 0.1;
+    const EASE_OUT: f32 = 4.0;
+
+    let (Ok((pivot, mut orbit, children)), Ok(context)) =
+        (pivot_query.get_single_mut(), rapier_context.get_single())
+    else {
+        return;
+    };
+
+    // sweep a small sphere from the pivot out to where the camera wants to be
+    let desired = pivot.rotation * orbit.offset();
+    let length = desired.length();
+    let predicate = |entity: Entity| !transparent_query.contains(entity);
+    let hit = context.cast_shape(
+        pivot.translation,
+        Quat::IDENTITY,
+        desired / length,
+        &Collider::ball(CAMERA_RADIUS),
+        ShapeCastOptions::with_max_time_of_impact(length),
+        QueryFilter::default().exclude_sensors().predicate(&predicate),
+    );
+
+    let target = match hit {
+        Some((_, hit)) => (hit.time_of_impact / length).max(MIN_BOOM),
+        None => 1.0,
+    };
+
+    // snap in so walls never show through, ease back out once clear
+    if target < orbit.boom {
+        orbit.boom = target;
+    } else {
+        let t = 1.0 - (-EASE_OUT * time.delta_secs()).exp();
+        orbit.boom = orbit.boom.lerp(target, t);
+    }
+
+    // first person pulls the boom all the way into the pivot
+    let offset = orbit.offset() * orbit.boom * (1.0 - orbit.first_person);
+    for child in children.iter() {
+        if let Ok(mut transform) = camera_query.get_mut(*child) {
+            transform.translation = offset;
+            transform.rotation = Quat::IDENTITY;
+        }
+    }
+}
+
+fn dialog_camera(
+    mut pivot_query: Query<(&Transform, &mut CameraOrbit, &Children)>,
+    mut camera_query: Query<&mut Transform, (With<Camera3d>, Without<CameraOrbit>)>,
+    player_query: Query<&Transform, (With<Player>, Without<CameraOrbit>, Without<Camera3d>)>,
+    speaker_query: Query<(&GlobalTransform, Option<&DialogCamera>)>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    mut last_shot: Local<Option<Transform>>,
+    time: Res<Time>,
+) {
+    const BLEND_SPEED: f32 = 4.0;
+
+    let (Ok((pivot, mut orbit, children)), Ok(state), Ok(player)) = (
+        pivot_query.get_single_mut(),
+        game_query.get_single(),
+        player_query.get_single(),
+    ) else {
+        return;
+    };
+
+    // pick the shot for the current line, falling back to the speaker's default
+    let framing = state.dialog_speaker.filter(|_| state.in_dialog).and_then(|speaker| {
+        let (speaker_transform, speaker_camera) = speaker_query.get(speaker).ok()?;
+        let shot = state
+            .current_dialog
+            .as_ref()
+            .and_then(|dialog| dialog.shot_for_line(state.current_dialog_line))
+            .or(speaker_camera.map(|camera| camera.0))
+            .unwrap_or(DialogShot::OverShoulder);
+        let player_head = player.translation + Vec3::Y * STANDING_HEIGHT;
+        Some(frame_shot(shot, player_head, speaker_transform.translation()))
+    });
+
+    let target = if framing.is_some() { 1.0 } else { 0.0 };
+    let t = 1.0 - (-BLEND_SPEED * time.delta_secs()).exp();
+    orbit.dialog = orbit.dialog.lerp(target, t);
+    if orbit.dialog < 0.001 {
+        orbit.dialog = 0.0;
+        return;
+    }
+
+    // keep the last shot while blending back out so the camera doesn't jump
+    if framing.is_some() {
+        *last_shot = framing;
+    }
+    let Some(shot) = *last_shot else {
+        return;
+    };
+
+    // shots are in world space, the camera is a child of the pivot
+    let local = Transform::from_matrix(pivot.compute_matrix().inverse() * shot.compute_matrix());
+    for child in children.iter() {
+        if let Ok(mut transform) = camera_query.get_mut(*child) {
+            transform.translation = transform.translation.lerp(local.translation, orbit.dialog);
+            transform.rotation = transform.rotation.slerp(local.rotation, orbit.dialog);
+        }
+    }
+}
+
+// world space camera transform for a dialog shot between the player and the speaker
+fn frame_shot(shot: DialogShot, player_head: Vec3, speaker: Vec3) -> Transform {
+    const SHOULDER_BACK: f32 = 2.0;
+    const SHOULDER_SIDE: f32 = 0.8;
+    const SHOULDER_UP: f32 = 0.3;
+    const TWO_SHOT_MIN_DISTANCE: f32 = 4.0;
+
+    let between = speaker - player_head;
+    let facing = Vec3::new(between.x, 0.0, between.z).normalize_or(Vec3::NEG_Z);
+    let side = facing.cross(Vec3::Y).normalize();
+
+    match shot {
+        DialogShot::OverShoulder => {
+            let position = player_head - facing * SHOULDER_BACK + side * SHOULDER_SIDE + Vec3::Y * SHOULDER_UP;
+            Transform::from_translation(position).looking_at(speaker, Vec3::Y)
+        }
+        DialogShot::TwoShot => {
+            let midpoint = (player_head + speaker) / 2.0;
+            let distance = (between.length() * 1.2).max(TWO_SHOT_MIN_DISTANCE);
+            let position = midpoint + side * distance + Vec3::Y;
+            Transform::from_translation(position).looking_at(midpoint, Vec3::Y)
+        }
+    }
+}
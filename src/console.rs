@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+
+use bevy::ecs::system::SystemId;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::gui_plugin::{GameState, GameStateMarker};
+
+// words after the command name, and what gets printed back
+pub type ConsoleArgs = Vec<String>;
+pub type ConsoleResult = Result<String, String>;
+
+pub struct ConsoleCommand {
+    pub usage: String,
+    system: SystemId<In<ConsoleArgs>, ConsoleResult>,
+    complete: Option<fn(&World, &[&str]) -> Vec<String>>,
+}
+
+// every command the console knows, keyed by name
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(pub BTreeMap<String, ConsoleCommand>);
+
+// lets any plugin add commands, the console itself is added by DebugPlugin
+pub trait ConsoleApp {
+    // command systems take In<ConsoleArgs> and return the text to print
+    fn add_console_command<M>(
+        &mut self,
+        name: &str,
+        usage: &str,
+        command: impl IntoSystem<In<ConsoleArgs>, ConsoleResult, M> + 'static,
+    ) -> &mut Self;
+
+    // candidates for the argument being typed, given the ones before it
+    fn add_console_completion(&mut self, name: &str, complete: fn(&World, &[&str]) -> Vec<String>) -> &mut Self;
+}
+
+impl ConsoleApp for App {
+    fn add_console_command<M>(
+        &mut self,
+        name: &str,
+        usage: &str,
+        command: impl IntoSystem<In<ConsoleArgs>, ConsoleResult, M> + 'static,
+    ) -> &mut Self {
+        let system = self.register_system(command);
+        self.init_resource::<ConsoleCommands>();
+        self.world_mut().resource_mut::<ConsoleCommands>().0.insert(
+            name.to_string(),
+            ConsoleCommand {
+                usage: usage.to_string(),
+                system,
+                complete: None,
+            },
+        );
+        self
+    }
+
+    fn add_console_completion(&mut self, name: &str, complete: fn(&World, &[&str]) -> Vec<String>) -> &mut Self {
+        self.init_resource::<ConsoleCommands>();
+        if let Some(command) = self.world_mut().resource_mut::<ConsoleCommands>().0.get_mut(name) {
+            command.complete = Some(complete);
+        }
+        self
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LineKind {
+    Input,
+    Output,
+    Error,
+}
+
+#[derive(Resource, Default)]
+pub struct Console {
+    input: String,
+    log: Vec<(LineKind, String)>,
+    history: Vec<String>,
+    history_index: Option<usize>, // position while browsing with up/down, None when editing a new line
+    submitted: Vec<String>,
+    complete_requested: bool,
+}
+
+impl Console {
+    fn print(&mut self, kind: LineKind, text: &str) {
+        const MAX_LINES: usize = 200;
+
+        self.log.extend(text.lines().map(|line| (kind, line.to_string())));
+        let overflow = self.log.len().saturating_sub(MAX_LINES);
+        self.log.drain(..overflow);
+    }
+}
+
+pub fn toggle_console(
+    mut state_query: Query<&mut GameState, With<GameStateMarker>>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    if input.just_pressed(KeyCode::Backquote) {
+        if let Ok(mut state) = state_query.get_single_mut() {
+            state.console_open = !state.console_open;
+        }
+    }
+}
+
+pub fn render_console(
+    mut contexts: EguiContexts,
+    mut console: ResMut<Console>,
+    state_query: Query<&GameState, With<GameStateMarker>>,
+) {
+    let Ok(state) = state_query.get_single() else {
+        return;
+    };
+    if !state.console_open {
+        return;
+    }
+
+    egui::Window::new("Console")
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 10.0])
+        .resizable(false)
+        .collapsible(false)
+        .fixed_size([700.0, 300.0])
+        .show(contexts.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(260.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    ui.set_min_width(ui.available_width());
+                    for (kind, line) in &console.log {
+                        let color = match kind {
+                            LineKind::Input => egui::Color32::LIGHT_GRAY,
+                            LineKind::Output => egui::Color32::WHITE,
+                            LineKind::Error => egui::Color32::LIGHT_RED,
+                        };
+                        ui.label(egui::RichText::new(line).monospace().color(color));
+                    }
+                });
+
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut console.input)
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(f32::INFINITY)
+                    .lock_focus(true),
+            );
+            // the toggle key gets typed into the field on the frame it opens
+            console.input.retain(|c| c != '`');
+            response.request_focus();
+
+            let (enter, tab, up, down) = ui.input(|input| {
+                (
+                    input.key_pressed(egui::Key::Enter),
+                    input.key_pressed(egui::Key::Tab),
+                    input.key_pressed(egui::Key::ArrowUp),
+                    input.key_pressed(egui::Key::ArrowDown),
+                )
+            });
+
+            if enter {
+                let line = std::mem::take(&mut console.input);
+                if !line.trim().is_empty() {
+                    console.submitted.push(line);
+                }
+                console.history_index = None;
+            } else if tab {
+                console.complete_requested = true;
+            } else if up && !console.history.is_empty() {
+                let index = console.history_index.map_or(console.history.len() - 1, |index| index.saturating_sub(1));
+                console.history_index = Some(index);
+                console.input = console.history[index].clone();
+            } else if down {
+                if let Some(index) = console.history_index {
+                    if index + 1 < console.history.len() {
+                        console.history_index = Some(index + 1);
+                        console.input = console.history[index + 1].clone();
+                    } else {
+                        console.history_index = None;
+                        console.input.clear();
+                    }
+                }
+            }
+        });
+}
+
+// exclusive so commands can touch anything, runs after render_console has collected input
+pub fn run_console_commands(world: &mut World) {
+    let (submitted, complete_requested) = {
+        let mut console = world.resource_mut::<Console>();
+        (
+            std::mem::take(&mut console.submitted),
+            std::mem::take(&mut console.complete_requested),
+        )
+    };
+
+    for line in submitted {
+        {
+            let mut console = world.resource_mut::<Console>();
+            console.print(LineKind::Input, &format!("> {}", line));
+            // repeats of the last line don't clutter history
+            if console.history.last() != Some(&line) {
+                console.history.push(line.clone());
+            }
+        }
+
+        let result = run_console_command(world, &line);
+        let mut console = world.resource_mut::<Console>();
+        match result {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => console.print(LineKind::Output, &output),
+            Err(err) => console.print(LineKind::Error, &err),
+        }
+    }
+
+    if complete_requested {
+        complete_input(world);
+    }
+}
+
+pub fn run_console_command(world: &mut World, line: &str) -> ConsoleResult {
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        return Ok(String::new());
+    };
+    let args: ConsoleArgs = words.map(str::to_string).collect();
+
+    match name {
+        "help" => {
+            let commands = world.resource::<ConsoleCommands>();
+            let usages: Vec<&str> = commands.0.values().map(|command| command.usage.as_str()).collect();
+            Ok(format!("help\nclear\n{}", usages.join("\n")))
+        }
+        "clear" => {
+            world.resource_mut::<Console>().log.clear();
+            Ok(String::new())
+        }
+        _ => {
+            let Some(system) = world.resource::<ConsoleCommands>().0.get(name).map(|command| command.system) else {
+                return Err(format!("Unknown command: {}, try help", name));
+            };
+            world.run_system_with_input(system, args).map_err(|err| err.to_string())?
+        }
+    }
+}
+
+// completes the word under the cursor (always the last one), listing candidates when it's ambiguous
+fn complete_input(world: &mut World) {
+    let input = world.resource::<Console>().input.clone();
+    let mut words: Vec<&str> = input.split_whitespace().collect();
+    if input.ends_with(' ') || words.is_empty() {
+        words.push("");
+    }
+    let (current, before) = words.split_last().expect("at least one word");
+
+    let candidates: Vec<String> = if before.is_empty() {
+        let commands = world.resource::<ConsoleCommands>();
+        ["help", "clear"]
+            .into_iter()
+            .map(str::to_string)
+            .chain(commands.0.keys().cloned())
+            .collect()
+    } else {
+        let commands = world.resource::<ConsoleCommands>();
+        match commands.0.get(before[0]).and_then(|command| command.complete) {
+            Some(complete) => complete(world, &before[1..]),
+            None => Vec::new(),
+        }
+    };
+
+    let matches: Vec<&String> = candidates.iter().filter(|candidate| candidate.starts_with(current)).collect();
+    let Some(first) = matches.first() else {
+        return;
+    };
+
+    // longest prefix every match shares
+    let mut common = first.as_str();
+    for candidate in &matches[1..] {
+        let shared = common
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((index, c), _)| index + c.len_utf8());
+        common = &common[..shared];
+    }
+
+    let mut completed = before.join(" ");
+    if !completed.is_empty() {
+        completed.push(' ');
+    }
+    completed.push_str(common);
+    if matches.len() == 1 {
+        completed.push(' ');
+    }
+
+    let listing = (matches.len() > 1).then(|| matches.iter().map(|candidate| candidate.as_str()).collect::<Vec<_>>().join("  "));
+    let mut console = world.resource_mut::<Console>();
+    if let Some(listing) = listing {
+        console.print(LineKind::Output, &listing);
+    }
+    console.input = completed;
+}
@@ -1,27 +1,645 @@
+use std::time::Instant;
+
+use bevy::asset::LoadState;
+use bevy::diagnostic::{
+    Diagnostic, DiagnosticPath, Diagnostics, DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
+    RegisterDiagnostic,
+};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 use bevy_rapier3d::prelude::*;
 
 use crate::{
+    camera_plugin::{CameraOrbit, CameraTransparent},
+    console::{render_console, run_console_commands, toggle_console, Console, ConsoleApp, ConsoleArgs, ConsoleResult},
+    inspector::{draw_selection, pick_entity, render_inspector, Inspector},
     gui_plugin::{GameState, GameStateMarker},
-    player_plugin::{Player, PlayerPhysics},
+    mechanics::dialog::{DialogAsset, DialogEvent},
+    mechanics::flags::{set_flag, FlagChanged, StoryFlags},
+    mechanics::inventory::{Inventory, ItemCollected, ItemPickup},
+    mechanics::room::{CurrentRoom, LoadRoom, Room, RoomAsset},
+    mechanics::save::SaveEvent,
+    mechanics::visuals::ColliderVisual,
+    mechanics::door::Door,
+    mechanics::navigation::{NavAgent, NavMesh},
+    mechanics::trigger::TriggerState,
+    player_plugin::{
+        DialogTrigger, DoorTrigger, Interactable, InteractionFocus, ItemTrigger, MovementMode, Player, PlayerPhysics,
+        ToggleNoclip, INT_RADIUS,
+    },
+    replay_plugin::{replay_path, Recording, Replay, ReplayEvent, ReplayPlugin},
 };
 
 pub struct DebugPlugin;
 
 impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, player_debug);
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        if !app.is_plugin_added::<EntityCountDiagnosticsPlugin>() {
+            app.add_plugins(EntityCountDiagnosticsPlugin);
+        }
+        if !app.is_plugin_added::<ReplayPlugin>() {
+            app.add_plugins(ReplayPlugin);
+        }
+
+        app.init_resource::<Console>()
+            .init_resource::<ConsoleDialog>()
+            .init_resource::<DebugOverlays>()
+            .init_resource::<Inspector>()
+            .init_resource::<StepTimer>()
+            .register_diagnostic(Diagnostic::new(RAPIER_STEP_TIME).with_suffix("ms"))
+            .add_systems(PostUpdate, (
+                start_step_timer.after(PhysicsSet::SyncBackend).before(PhysicsSet::StepSimulation),
+                end_step_timer.after(PhysicsSet::StepSimulation).before(PhysicsSet::Writeback),
+            ))
+            .add_systems(Update, (
+                (toggle_overlays, player_debug).chain(),
+                (pick_entity, render_inspector).chain().after(toggle_overlays),
+                (toggle_console, render_console, run_console_commands).chain(),
+                start_console_dialog,
+                draw_interaction_range,
+                draw_sensors,
+                draw_navmesh,
+                draw_selection,
+            ))
+            .add_console_command("tp", "tp <x> <y> <z>", tp_command)
+            .add_console_command("room", "room <name> [spawn point]", room_command)
+            .add_console_completion("room", |_, _| asset_names("rooms", ".room.ron"))
+            .add_console_command("give", "give <item> [count]", give_command)
+            .add_console_completion("give", |world, _| world.resource::<Inventory>().items.keys().cloned().collect())
+            .add_console_command("flag", "flag set|clear|get <name>, flag list", flag_command)
+            .add_console_completion("flag", complete_flag)
+            .add_console_command("dialog", "dialog start <name>", dialog_command)
+            .add_console_completion("dialog", complete_dialog)
+            .add_console_command("timescale", "timescale <speed>", timescale_command)
+            .add_console_command("save", "save", save_command)
+            .add_console_command("load", "load", load_command)
+            .add_console_command("spawn", "spawn box|ball|item <name>", spawn_command)
+            .add_console_completion("spawn", |_, before| match before {
+                [] => vec!["box".to_string(), "ball".to_string(), "item".to_string()],
+                _ => Vec::new(),
+            })
+            .add_console_command("noclip", "noclip", noclip_command)
+            .add_console_command("record", "record start, record stop <name>", record_command)
+            .add_console_completion("record", |_, before| match before {
+                [] => vec!["start".to_string(), "stop".to_string()],
+                _ => Vec::new(),
+            })
+            .add_console_command("replay", "replay <name>, replay stop", replay_command)
+            .add_console_completion("replay", |_, before| match before {
+                [] => file_names("replays", ".replay.ron"),
+                _ => Vec::new(),
+            });
+    }
+}
+
+// which debug views are showing, each has its own function key
+#[derive(Resource)]
+pub struct DebugOverlays {
+    pub player_window: bool,
+    pub interaction: bool,
+    pub triggers: bool,
+    pub navmesh: bool,
+    pub inspector: bool,
+}
+
+impl Default for DebugOverlays {
+    fn default() -> Self {
+        Self {
+            player_window: true,
+            interaction: false,
+            triggers: false,
+            navmesh: false,
+            inspector: false,
+        }
+    }
+}
+
+// wall time rapier spends in its step, timed around PhysicsSet::StepSimulation
+pub const RAPIER_STEP_TIME: DiagnosticPath = DiagnosticPath::const_new("rapier/step_time");
+
+#[derive(Resource, Default)]
+struct StepTimer(Option<Instant>);
+
+fn start_step_timer(mut timer: ResMut<StepTimer>) {
+    timer.0 = Some(Instant::now());
+}
+
+fn end_step_timer(mut timer: ResMut<StepTimer>, mut diagnostics: Diagnostics) {
+    if let Some(start) = timer.0.take() {
+        diagnostics.add_measurement(&RAPIER_STEP_TIME, || start.elapsed().as_secs_f64() * 1000.0);
     }
 }
 
+// dialog asked for from the console, started once it has loaded
+#[derive(Resource, Default)]
+struct ConsoleDialog(Option<Handle<DialogAsset>>);
+
+fn parse_arg<T: std::str::FromStr>(args: &[String], index: usize, name: &str) -> Result<T, String> {
+    let arg = args.get(index).ok_or_else(|| format!("Missing {}", name))?;
+    arg.parse().map_err(|_| format!("Bad {}: {}", name, arg))
+}
+
+// names of the asset files in assets/<folder> with the given extension, for completion
+fn asset_names(folder: &str, extension: &str) -> Vec<String> {
+    file_names(&format!("assets/{}", folder), extension)
+}
+
+fn file_names(dir: &str, extension: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str()?.strip_suffix(extension).map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+fn tp_command(
+    In(args): In<ConsoleArgs>,
+    mut player_query: Query<(&mut Transform, &mut PlayerPhysics, &mut MovementMode), With<Player>>,
+) -> ConsoleResult {
+    let position = Vec3::new(
+        parse_arg(&args, 0, "x")?,
+        parse_arg(&args, 1, "y")?,
+        parse_arg(&args, 2, "z")?,
+    );
+    let (mut transform, mut physics, mut mode) = player_query.get_single_mut().map_err(|_| "No player".to_string())?;
+    transform.translation = position;
+    physics.velocity = Vec3::ZERO;
+    *mode = MovementMode::Walking;
+    Ok(format!("Teleported to {}", position))
+}
+
+fn room_command(In(args): In<ConsoleArgs>, mut load_writer: EventWriter<LoadRoom>) -> ConsoleResult {
+    let room: String = parse_arg(&args, 0, "room name")?;
+    load_writer.send(LoadRoom {
+        room: room.clone(),
+        spawn_point: args.get(1).cloned(),
+    });
+    Ok(format!("Loading room {}", room))
+}
+
+fn give_command(
+    In(args): In<ConsoleArgs>,
+    mut inventory: ResMut<Inventory>,
+    mut collected_writer: EventWriter<ItemCollected>,
+) -> ConsoleResult {
+    let item: String = parse_arg(&args, 0, "item")?;
+    let count = if args.len() > 1 { parse_arg(&args, 1, "count")? } else { 1 };
+    inventory.add(&item, count);
+    // quests and notices react as if it had been picked up
+    collected_writer.send(ItemCollected { item: item.clone(), count });
+    Ok(format!("Gave {} x{}, now {}", item, count, inventory.count(&item)))
+}
+
+fn flag_command(
+    In(args): In<ConsoleArgs>,
+    mut flags: ResMut<StoryFlags>,
+    mut flag_writer: EventWriter<FlagChanged>,
+) -> ConsoleResult {
+    let action: String = parse_arg(&args, 0, "action")?;
+    if action == "list" {
+        if flags.0.is_empty() {
+            return Ok("No flags set".to_string());
+        }
+        return Ok(flags.0.iter().cloned().collect::<Vec<_>>().join("\n"));
+    }
+
+    let flag: String = parse_arg(&args, 1, "flag name")?;
+    match action.as_str() {
+        "set" => set_flag(&mut flags, &mut flag_writer, &flag, true),
+        "clear" => set_flag(&mut flags, &mut flag_writer, &flag, false),
+        "get" => {}
+        _ => return Err(format!("Unknown flag action: {}", action)),
+    }
+    Ok(format!("{}: {}", flag, flags.is_set(&flag)))
+}
+
+fn complete_flag(world: &World, before: &[&str]) -> Vec<String> {
+    match before {
+        [] => ["set", "clear", "get", "list"].map(str::to_string).to_vec(),
+        [_] => world.resource::<StoryFlags>().0.iter().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn dialog_command(
+    In(args): In<ConsoleArgs>,
+    mut console_dialog: ResMut<ConsoleDialog>,
+    asset_server: Res<AssetServer>,
+) -> ConsoleResult {
+    let action: String = parse_arg(&args, 0, "action")?;
+    if action != "start" {
+        return Err(format!("Unknown dialog action: {}", action));
+    }
+    let name: String = parse_arg(&args, 1, "dialog name")?;
+    console_dialog.0 = Some(asset_server.load(format!("dialogs/{}.dialog.ron", name)));
+    Ok(format!("Starting dialog {}", name))
+}
+
+fn complete_dialog(_: &World, before: &[&str]) -> Vec<String> {
+    match before {
+        [] => vec!["start".to_string()],
+        [_] => asset_names("dialogs", ".dialog.ron"),
+        _ => Vec::new(),
+    }
+}
+
+// the player stands in as the speaker, there's nobody to talk to
+fn start_console_dialog(
+    mut console_dialog: ResMut<ConsoleDialog>,
+    mut dialog_writer: EventWriter<DialogEvent>,
+    player_query: Query<Entity, With<Player>>,
+    dialog_assets: Res<Assets<DialogAsset>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(handle) = &console_dialog.0 else {
+        return;
+    };
+
+    if let Some(dialog) = dialog_assets.get(handle) {
+        if let Ok(player) = player_query.get_single() {
+            dialog_writer.send(DialogEvent::DialogData(dialog.clone(), player));
+        }
+        console_dialog.0 = None;
+    } else if let Some(LoadState::Failed(err)) = asset_server.get_load_state(handle) {
+        warn!("Failed to load dialog: {}", err);
+        console_dialog.0 = None;
+    }
+}
+
+fn noclip_command(In(_): In<ConsoleArgs>, mut noclip_writer: EventWriter<ToggleNoclip>) -> ConsoleResult {
+    noclip_writer.send(ToggleNoclip);
+    Ok(String::new())
+}
+
+// input from the next frame on, until stopped, is saved to replays/<name>.replay.ron
+fn record_command(
+    In(args): In<ConsoleArgs>,
+    replay: Res<Replay>,
+    mut replay_writer: EventWriter<ReplayEvent>,
+) -> ConsoleResult {
+    let action: String = parse_arg(&args, 0, "action")?;
+    match action.as_str() {
+        "start" => {
+            if replay.is_recording() || replay.is_playing() {
+                return Err("Already recording or replaying".to_string());
+            }
+            replay_writer.send(ReplayEvent::Record);
+            Ok("Recording, record stop <name> to save it".to_string())
+        }
+        "stop" => {
+            if !replay.is_recording() {
+                return Err("Not recording".to_string());
+            }
+            let name: String = parse_arg(&args, 1, "recording name")?;
+            replay_writer.send(ReplayEvent::StopRecording(Some(replay_path(&name))));
+            Ok(String::new())
+        }
+        _ => Err(format!("Unknown record action: {}", action)),
+    }
+}
+
+fn replay_command(
+    In(args): In<ConsoleArgs>,
+    replay: Res<Replay>,
+    mut replay_writer: EventWriter<ReplayEvent>,
+) -> ConsoleResult {
+    let name: String = parse_arg(&args, 0, "recording name")?;
+    if name == "stop" {
+        replay_writer.send(ReplayEvent::StopPlayback);
+        return Ok(String::new());
+    }
+    if replay.is_recording() || replay.is_playing() {
+        return Err("Already recording or replaying".to_string());
+    }
+    let recording = Recording::read(&replay_path(&name))?;
+    replay_writer.send(ReplayEvent::Play(recording));
+    Ok(String::new())
+}
+
+fn timescale_command(In(args): In<ConsoleArgs>, mut time: ResMut<Time<Virtual>>) -> ConsoleResult {
+    let speed: f32 = parse_arg(&args, 0, "speed")?;
+    if speed < 0.0 {
+        return Err("Speed can't be negative".to_string());
+    }
+    time.set_relative_speed(speed);
+    Ok(format!("Time scale {}", speed))
+}
+
+fn save_command(In(_): In<ConsoleArgs>, mut save_writer: EventWriter<SaveEvent>) -> ConsoleResult {
+    save_writer.send(SaveEvent::Save);
+    Ok(String::new())
+}
+
+fn load_command(In(_): In<ConsoleArgs>, mut save_writer: EventWriter<SaveEvent>) -> ConsoleResult {
+    save_writer.send(SaveEvent::Load);
+    Ok(String::new())
+}
+
+// drops a prefab a couple of metres in front of the player, parented to the room so it goes with it
+fn spawn_command(
+    In(args): In<ConsoleArgs>,
+    mut commands: Commands,
+    player_query: Query<&Transform, With<Player>>,
+    orbit_query: Query<&Transform, (With<CameraOrbit>, Without<Player>)>,
+    room_query: Query<Entity, With<Room>>,
+) -> ConsoleResult {
+    const DISTANCE: f32 = 2.5;
+
+    let prefab: String = parse_arg(&args, 0, "prefab")?;
+    let player = player_query.get_single().map_err(|_| "No player".to_string())?;
+    let forward = orbit_query
+        .get_single()
+        .map(|orbit| Vec3::new(orbit.forward().x, 0.0, orbit.forward().z).normalize_or(Vec3::NEG_Z))
+        .unwrap_or(Vec3::NEG_Z);
+    let transform = Transform::from_translation(player.translation + forward * DISTANCE + Vec3::Y);
+
+    let mut entity = match prefab.as_str() {
+        "box" => commands.spawn((
+            RigidBody::Dynamic,
+            Collider::cuboid(0.4, 0.4, 0.4),
+            ColliderVisual(StandardMaterial::from_color(Color::srgb(0.6, 0.45, 0.3))),
+        )),
+        "ball" => commands.spawn((
+            RigidBody::Dynamic,
+            Collider::ball(0.4),
+            ColliderVisual(StandardMaterial::from_color(Color::srgb(0.3, 0.5, 0.8))),
+        )),
+        "item" => {
+            let item: String = parse_arg(&args, 1, "item")?;
+            commands.spawn((
+                Collider::ball(0.4),
+                Interactable,
+                ItemTrigger,
+                ItemPickup {
+                    item,
+                    count: 1,
+                    key: "console".to_string(),
+                },
+                ColliderVisual(StandardMaterial::from_color(Color::srgb(0.9, 0.8, 0.2))),
+            ))
+        }
+        _ => return Err(format!("Unknown prefab: {}", prefab)),
+    };
+    entity.insert((Name::new(prefab.clone()), transform, CameraTransparent));
+
+    if let Ok(room) = room_query.get_single() {
+        entity.set_parent(room);
+    }
+    Ok(format!("Spawned {}", prefab))
+}
+
+// F1 player window, F2 physics wireframes, F3 interaction range, F4 sensors, F5 navmesh, F6 inspector
+fn toggle_overlays(
+    mut overlays: ResMut<DebugOverlays>,
+    mut debug_render: ResMut<DebugRenderContext>,
+    input: Res<ButtonInput<KeyCode>>,
+) {
+    if input.just_pressed(KeyCode::F1) {
+        overlays.player_window = !overlays.player_window;
+    }
+    if input.just_pressed(KeyCode::F2) {
+        debug_render.enabled = !debug_render.enabled;
+    }
+    if input.just_pressed(KeyCode::F3) {
+        overlays.interaction = !overlays.interaction;
+    }
+    if input.just_pressed(KeyCode::F4) {
+        overlays.triggers = !overlays.triggers;
+    }
+    if input.just_pressed(KeyCode::F5) {
+        overlays.navmesh = !overlays.navmesh;
+    }
+    if input.just_pressed(KeyCode::F6) {
+        overlays.inspector = !overlays.inspector;
+    }
+}
+
+// reach of the interact key, what's inside it colored by kind, and the nearest one
+fn draw_interaction_range(
+    mut gizmos: Gizmos,
+    overlays: Res<DebugOverlays>,
+    focus: Res<InteractionFocus>,
+    player_query: Query<&Transform, With<Player>>,
+    interactable_query: Query<(&GlobalTransform, Option<&Collider>, Has<DialogTrigger>, Has<DoorTrigger>, Has<ItemTrigger>)>,
+) {
+    const TARGET_COLOR: Color = Color::WHITE;
+
+    if !overlays.interaction {
+        return;
+    }
+    let Ok(transform) = player_query.get_single() else {
+        return;
+    };
+
+    let range_color = if focus.target.is_some() { Color::srgb(0.3, 0.8, 1.0) } else { Color::srgb(0.3, 0.4, 0.5) };
+    gizmos.sphere(Isometry3d::from_translation(transform.translation), INT_RADIUS, range_color);
+
+    for entity in &focus.in_range {
+        let Ok((global_transform, collider, dialog, door, item)) = interactable_query.get(*entity) else {
+            continue;
+        };
+        let color = if dialog {
+            Color::srgb(0.3, 1.0, 0.4)
+        } else if door {
+            Color::srgb(0.3, 0.5, 1.0)
+        } else if item {
+            Color::srgb(1.0, 0.85, 0.2)
+        } else {
+            Color::srgb(0.6, 0.6, 0.6)
+        };
+        let position = global_transform.translation();
+        let radius = collider.and_then(|collider| collider.as_ball()).map_or(0.5, |ball| ball.radius());
+        gizmos.sphere(Isometry3d::from_translation(position), radius, color);
+        gizmos.line(transform.translation, position, color);
+
+        if focus.target == Some(*entity) {
+            // ring around it and an arrow pointing down onto it
+            gizmos.sphere(Isometry3d::from_translation(position), radius + 0.15, TARGET_COLOR);
+            let top = position + Vec3::Y * (radius + 1.0);
+            gizmos.arrow(top, position + Vec3::Y * (radius + 0.2), TARGET_COLOR);
+        }
+    }
+}
+
+// trigger volumes yellow (green while occupied), portals blue, anything else that's a sensor grey
+fn draw_sensors(
+    mut gizmos: Gizmos,
+    overlays: Res<DebugOverlays>,
+    sensor_query: Query<(&Collider, &GlobalTransform, Option<&TriggerState>, Has<Door>), With<Sensor>>,
+) {
+    if !overlays.triggers {
+        return;
+    }
+
+    for (collider, global_transform, trigger, door) in sensor_query.iter() {
+        let color = match trigger {
+            Some(state) if !state.occupants.is_empty() => Color::srgb(0.3, 1.0, 0.3),
+            Some(_) => Color::srgb(1.0, 0.85, 0.2),
+            None if door => Color::srgb(0.3, 0.5, 1.0),
+            None => Color::srgb(0.6, 0.6, 0.6),
+        };
+
+        draw_collider(&mut gizmos, collider, global_transform, color);
+    }
+}
+
+// boxes and balls drawn as themselves, any other shape as a cross at its origin
+pub fn draw_collider(gizmos: &mut Gizmos, collider: &Collider, global_transform: &GlobalTransform, color: Color) {
+    let transform = global_transform.compute_transform();
+    if let Some(cuboid) = collider.as_cuboid() {
+        gizmos.cuboid(transform.with_scale(transform.scale * cuboid.half_extents() * 2.0), color);
+    } else if let Some(ball) = collider.as_ball() {
+        gizmos.sphere(Isometry3d::new(transform.translation, transform.rotation), ball.radius(), color);
+    } else {
+        gizmos.cross(Isometry3d::from_translation(transform.translation), 0.5, color);
+    }
+}
+
+// walkable cells and the paths agents are following
+fn draw_navmesh(
+    mut gizmos: Gizmos,
+    overlays: Res<DebugOverlays>,
+    navmesh: Res<NavMesh>,
+    agent_query: Query<(&Transform, &NavAgent)>,
+) {
+    const LIFT: f32 = 0.05; // keep the cells from z-fighting with the floor
+
+    if !overlays.navmesh {
+        return;
+    }
+
+    if let Some(grid) = &navmesh.grid {
+        let size = Vec2::splat(grid.cell_size() * 0.8);
+        let flat = Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+        for cell in grid.walkable_cells() {
+            gizmos.rect(Isometry3d::new(cell + Vec3::Y * LIFT, flat), size, Color::srgba(0.2, 0.9, 0.5, 0.5));
+        }
+    }
+
+    for (transform, agent) in agent_query.iter() {
+        if agent.path().is_empty() {
+            continue;
+        }
+        let points = std::iter::once(transform.translation).chain(agent.path().iter().copied());
+        gizmos.linestrip(points.map(|point| point + Vec3::Y * LIFT), Color::srgb(1.0, 0.3, 0.9));
+    }
+}
+
+// everything the performance section reads, kept together to stop player_debug growing more params
+#[derive(SystemParam)]
+struct PerformanceStats<'w, 's> {
+    diagnostics: Res<'w, DiagnosticsStore>,
+    rapier_context: Query<'w, 's, &'static RapierContext>,
+    collider_query: Query<'w, 's, (), With<Collider>>,
+    body_query: Query<'w, 's, (), With<RigidBody>>,
+    current_room: Res<'w, CurrentRoom>,
+    meshes: Res<'w, Assets<Mesh>>,
+    materials: Res<'w, Assets<StandardMaterial>>,
+    rooms: Res<'w, Assets<RoomAsset>>,
+    dialogs: Res<'w, Assets<DialogAsset>>,
+}
+
+impl PerformanceStats<'_, '_> {
+    fn value(&self, path: &DiagnosticPath) -> f64 {
+        self.diagnostics.get(path).and_then(|diagnostic| diagnostic.smoothed()).unwrap_or(0.0)
+    }
+
+    fn show(&self, ui: &mut egui::Ui) {
+        ui.label(format!(
+            "FPS: {:.0} ({:.2} ms)",
+            self.value(&FrameTimeDiagnosticsPlugin::FPS),
+            self.value(&FrameTimeDiagnosticsPlugin::FRAME_TIME),
+        ));
+        let frame_times: Vec<f64> = self
+            .diagnostics
+            .get(&FrameTimeDiagnosticsPlugin::FRAME_TIME)
+            .map(|diagnostic| diagnostic.values().copied().collect())
+            .unwrap_or_default();
+        frame_time_graph(ui, &frame_times);
+
+        ui.label(format!("Entities: {:.0}", self.value(&EntityCountDiagnosticsPlugin::ENTITY_COUNT)));
+        ui.label(format!("Rapier step: {:.2} ms", self.value(&RAPIER_STEP_TIME)));
+        ui.label(format!(
+            "Colliders: {}, bodies: {}",
+            self.collider_query.iter().count(),
+            self.body_query.iter().count(),
+        ));
+        if let Ok(context) = self.rapier_context.get_single() {
+            let contacts = context.contact_pairs().filter(|pair| pair.has_any_active_contact()).count();
+            ui.label(format!("Active contact pairs: {}", contacts));
+        }
+
+        ui.add_space(1.25);
+        ui.label(match (&self.current_room.name, self.current_room.is_loading()) {
+            (_, true) => "Room: loading".to_string(),
+            (Some(name), false) => format!("Room: {} loaded", name),
+            (None, false) => "Room: none".to_string(),
+        });
+        ui.label(format!(
+            "Assets: {} rooms, {} dialogs, {} meshes, {} materials",
+            self.rooms.len(),
+            self.dialogs.len(),
+            self.meshes.len(),
+            self.materials.len(),
+        ));
+    }
+}
+
+// last couple of seconds of frame times, with lines at the 60 and 30 fps budgets so spikes stand out
+fn frame_time_graph(ui: &mut egui::Ui, frame_times: &[f64]) {
+    const HEIGHT: f32 = 60.0;
+    const MAX_MS: f32 = 50.0;
+    const SPIKE_MS: f64 = 1000.0 / 30.0;
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), HEIGHT), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, egui::Color32::from_black_alpha(120));
+
+    let height_of = |ms: f64| rect.bottom() - (ms as f32).min(MAX_MS) / MAX_MS * HEIGHT;
+    for budget in [1000.0 / 60.0, 1000.0 / 30.0] {
+        painter.hline(rect.x_range(), height_of(budget), egui::Stroke::new(1.0, egui::Color32::DARK_GRAY));
+    }
+
+    if frame_times.len() < 2 {
+        return;
+    }
+    let step = rect.width() / (frame_times.len() - 1) as f32;
+    let points: Vec<egui::Pos2> = frame_times
+        .iter()
+        .enumerate()
+        .map(|(index, ms)| egui::pos2(rect.left() + index as f32 * step, height_of(*ms)))
+        .collect();
+    for (point, ms) in points.iter().zip(frame_times) {
+        if *ms > SPIKE_MS {
+            painter.circle_filled(*point, 2.0, egui::Color32::LIGHT_RED);
+        }
+    }
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, egui::Color32::LIGHT_GREEN)));
+}
+
 fn player_debug(
     mut contexts: EguiContexts,
-    rapier_context: Query<(&KinematicCharacterControllerOutput, &Transform, &PlayerPhysics), With<Player>>,
+    rapier_context: Query<(&KinematicCharacterControllerOutput, &Transform, &PlayerPhysics, &MovementMode), With<Player>>,
     game_query: Query<&GameState, With<GameStateMarker>>,
+    current_room: Res<CurrentRoom>,
+    mut overlays: ResMut<DebugOverlays>,
+    mut debug_render: ResMut<DebugRenderContext>,
+    performance: PerformanceStats,
 ) {
+    if !overlays.player_window {
+        return;
+    }
+
     if let Ok(state) = game_query.get_single() {
-        if let Ok((player_context, transform, physics)) = rapier_context.get_single() {
+        if let Ok((player_context, transform, physics, mode)) = rapier_context.get_single() {
             egui::Window::new("Debug")
                 .anchor(egui::Align2::RIGHT_TOP, [0.0, 0.0])
                 .resizable(false)
@@ -38,6 +656,23 @@ fn player_debug(
                             "No Active Dialog".to_string()
                         });
                         ui.label(format!("Current Line: {}", (state.current_dialog_line + 1).to_string()));
+                        ui.label(format!("Room: {}", current_room.name.as_deref().unwrap_or("Loading")));
+                        ui.add_space(5.0);
+
+                        ui.heading("Overlays");
+                        ui.add_space(1.25);
+                        ui.checkbox(&mut debug_render.enabled, "[F2] Physics wireframes");
+                        ui.checkbox(&mut overlays.interaction, "[F3] Interaction range");
+                        ui.checkbox(&mut overlays.triggers, "[F4] Sensors and triggers");
+                        ui.checkbox(&mut overlays.navmesh, "[F5] Navmesh");
+                        ui.checkbox(&mut overlays.inspector, "[F6] Inspector");
+                        ui.label("[F1] Hide this window");
+
+                        ui.add_space(5.0);
+
+                        egui::CollapsingHeader::new("Performance")
+                            .default_open(false)
+                            .show(ui, |ui| performance.show(ui));
 
                         ui.add_space(5.0);
 
@@ -46,6 +681,8 @@ fn player_debug(
                         ui.label(format!("Position Y: {:.6}", transform.translation.y));
                         ui.label(format!("Velocity: {:.6}", physics.velocity));
                         ui.label(format!("Grounded: {}", player_context.grounded.to_string()));
+                        ui.label(format!("Crouching: {}", physics.crouching.to_string()));
+                        ui.label(format!("Movement Mode: {:?}", mode));
                         ui.label(format!("Sliding: {}", player_context.is_sliding_down_slope.to_string()));
                         ui.label(format!("Desired Translation: {:.6}", player_context.desired_translation));
                         ui.label(format!("Effective Translation: {:.6}", player_context.effective_translation));
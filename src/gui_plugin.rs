@@ -1,36 +1,74 @@
 use bevy::prelude::*;
 use bevy::input::mouse::MouseButton;
 use bevy::app::AppExit;
+use bevy::window::{CursorGrabMode, PrimaryWindow, WindowFocused};
 use bevy_egui::{egui::{self, Color32, RichText}, EguiContexts};
 
 use crate::mechanics::dialog::{DialogAsset, DialogEvent};
+use crate::mechanics::save::SaveEvent;
+use crate::settings_plugin::{CameraMode, ChangeSettings, SaveSettings, Settings};
 
+// game state and the logic behind the menus and dialog, runs without a window
 pub struct GuiPlugin;
 
 impl Plugin for GuiPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<GameEvent>()
+        .add_event::<ChangeSettings>()
         .add_systems(Startup, setup_gui_plugin)
         .add_systems(Update, (
-            handle_pause, 
+            read_game_input,
+            start_dialog,
+            apply_settings_changes,
             handle_game_events,
+        ).chain());
+    }
+}
+
+// egui windows and cursor handling, needs EguiPlugin and a primary window
+pub struct GuiRenderPlugin;
+
+impl Plugin for GuiRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            handle_cursor_grab.after(handle_game_events),
             render_pause_menu,
-            render_dialog_box,
+            render_settings_menu,
+            render_dialog_box.after(handle_game_events),
         ));
     }
 }
 
-#[derive(Event)]
-enum GameEvent {
+// intents from input or any UI, only handle_game_events acts on them
+#[derive(Event, Clone, Copy, PartialEq, Debug)]
+pub enum GameEvent {
     TogglePause,
+    ToggleSettings,
+    AdvanceDialog,
+    ToggleJournal,
+    ToggleInventory,
+    Quit,
 }
 
 #[derive(Component)]
 pub struct GameState {
     pub paused: bool,
+    pub settings_open: bool,
     pub in_dialog: bool,
+    pub in_transition: bool,
+    pub journal_open: bool,
+    pub inventory_open: bool,
+    pub console_open: bool,
     pub current_dialog_line: usize,
-    pub current_dialog: Option<DialogAsset>
+    pub current_dialog: Option<DialogAsset>,
+    pub dialog_speaker: Option<Entity>,
+}
+
+impl GameState {
+    // true while the player is actually controlling the character, no menus or dialog up
+    pub fn in_gameplay(&self) -> bool {
+        !self.paused && !self.in_dialog && !self.in_transition && !self.journal_open && !self.inventory_open && !self.console_open
+    }
 }
 
 #[derive(Component)]
@@ -40,47 +78,165 @@ fn setup_gui_plugin(mut commands: Commands) {
     commands.spawn((
         GameState { 
             paused: false,
+            settings_open: false,
             in_dialog: false,
+            in_transition: false,
+            journal_open: false,
+            inventory_open: false,
+            console_open: false,
             current_dialog_line: 0,
             current_dialog: None,
+            dialog_speaker: None,
         },
         GameStateMarker,
     ));
 }
 
-fn handle_pause(
+fn read_game_input(
     mut event_writer: EventWriter<GameEvent>,
+    state_query: Query<&GameState>,
     input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
 ) {
     if input.just_pressed(KeyCode::Escape) {
         event_writer.send(GameEvent::TogglePause);
     }
+
+    if let Ok(state) = state_query.get_single() {
+        // J and I are just letters while typing in the console
+        if input.just_pressed(KeyCode::KeyJ) && !state.console_open {
+            event_writer.send(GameEvent::ToggleJournal);
+        }
+        if input.just_pressed(KeyCode::KeyI) && !state.console_open {
+            event_writer.send(GameEvent::ToggleInventory);
+        }
+        if state.in_dialog
+            && !state.paused
+            && (input.just_pressed(KeyCode::Space) || mouse_input.just_pressed(MouseButton::Left))
+        {
+            event_writer.send(GameEvent::AdvanceDialog);
+        }
+    }
+}
+
+// runs after read_game_input, so the key that opened a dialog can't also skip its first line
+fn start_dialog(
+    mut dialog_event: EventReader<DialogEvent>,
+    mut state_query: Query<&mut GameState>,
+) {
+    let Ok(mut state) = state_query.get_single_mut() else {
+        return;
+    };
+
+    for event in dialog_event.read() {
+        if let DialogEvent::DialogData(dialog_asset, speaker) = event {
+            state.in_dialog = true;
+            state.current_dialog_line = 0;
+            state.current_dialog = Some(dialog_asset.clone());
+            state.dialog_speaker = Some(*speaker);
+        }
+    }
+}
+
+fn apply_settings_changes(
+    mut event_reader: EventReader<ChangeSettings>,
+    mut settings: ResMut<Settings>,
+) {
+    if let Some(ChangeSettings(changed)) = event_reader.read().last() {
+        *settings = changed.clone();
+    }
 }
 
 fn handle_game_events(
     mut event_reader: EventReader<GameEvent>,
     mut state_query: Query<&mut GameState>,
+    mut exit: EventWriter<AppExit>,
+    mut save_writer: EventWriter<SaveSettings>,
 ) {
     for event in event_reader.read() {
         if let Ok(mut game_state) = state_query.get_single_mut() {
             match event {
                 GameEvent::TogglePause => {
+                    // escape out of the settings menu keeps the changes like Back does
+                    if game_state.settings_open {
+                        save_writer.send(SaveSettings);
+                    }
                     game_state.paused = !game_state.paused;
-                    println!("Game paused: {}", game_state.paused);
+                    game_state.settings_open = false;
+                    info!("Game paused: {}", game_state.paused);
+                },
+                GameEvent::ToggleSettings => {
+                    if game_state.settings_open {
+                        save_writer.send(SaveSettings);
+                    }
+                    game_state.settings_open = !game_state.settings_open;
+                },
+                GameEvent::AdvanceDialog => {
+                    if !game_state.in_dialog {
+                        continue;
+                    }
+                    let line_count = game_state.current_dialog.as_ref().map_or(0, |dialog| dialog.lines.len());
+                    if game_state.current_dialog_line + 1 < line_count {
+                        game_state.current_dialog_line += 1;
+                    } else {
+                        game_state.in_dialog = false;
+                        game_state.current_dialog = None;
+                        game_state.current_dialog_line = 0;
+                        game_state.dialog_speaker = None;
+                    }
+                },
+                GameEvent::ToggleJournal => {
+                    // opens only from gameplay, closes from anywhere
+                    if game_state.journal_open || game_state.in_gameplay() {
+                        game_state.journal_open = !game_state.journal_open;
+                    }
+                },
+                GameEvent::ToggleInventory => {
+                    if game_state.inventory_open || game_state.in_gameplay() {
+                        game_state.inventory_open = !game_state.inventory_open;
+                    }
+                },
+                GameEvent::Quit => {
+                    exit.send(AppExit::Success);
                 },
             }
         }
     }
 }
 
+fn handle_cursor_grab(
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut focus_events: EventReader<WindowFocused>,
+    state_query: Query<Ref<GameState>, With<GameStateMarker>>,
+) {
+    // only touch the cursor on state transitions or focus changes
+    let focus_changed = focus_events.read().count() > 0;
+    let Ok(state) = state_query.get_single() else {
+        return;
+    };
+    if !state.is_changed() && !focus_changed {
+        return;
+    }
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        let grab = state.in_gameplay() && window.focused;
+        window.cursor_options.grab_mode = if grab {
+            CursorGrabMode::Locked
+        } else {
+            CursorGrabMode::None
+        };
+        window.cursor_options.visible = !grab;
+    }
+}
+
 fn render_pause_menu(
     mut contexts: EguiContexts,
     mut event_writer: EventWriter<GameEvent>,
-    mut exit: EventWriter<AppExit>,
+    mut save_writer: EventWriter<SaveEvent>,
     query: Query<&GameState>,
 ) {
     if let Ok(state) = query.get_single() {
-        if state.paused {
+        if state.paused && !state.settings_open {
             egui::Window::new("Pause Menu")
             .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
             .show(contexts.ctx_mut(), |ui| {
@@ -88,32 +244,78 @@ fn render_pause_menu(
                 if ui.button("Resume").clicked() {
                     event_writer.send(GameEvent::TogglePause);
                 }
+                if ui.button("Settings").clicked() {
+                    event_writer.send(GameEvent::ToggleSettings);
+                }
+                if ui.button("Save").clicked() {
+                    save_writer.send(SaveEvent::Save);
+                }
+                if ui.button("Load").clicked() {
+                    save_writer.send(SaveEvent::Load);
+                    event_writer.send(GameEvent::TogglePause);
+                }
                 if ui.button("Quit").clicked() {
-                    exit.send(AppExit::Success);
+                    event_writer.send(GameEvent::Quit);
                 }
             });
         }
     }
 }
 
-fn render_dialog_box(
+fn render_settings_menu(
     mut contexts: EguiContexts,
-    mut dialog_event: EventReader<DialogEvent>,
-    mut game_state: Query<&mut GameState>,
-    input: Res<ButtonInput<KeyCode>>,
-    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut event_writer: EventWriter<GameEvent>,
+    mut settings_writer: EventWriter<ChangeSettings>,
+    settings: Res<Settings>,
+    query: Query<&GameState>,
 ) {
-    if let Ok(mut state) = game_state.get_single_mut() {
-        for event in dialog_event.read() {
-            if let DialogEvent::DialogData(dialog_asset) = event {
-                state.in_dialog = true;
-                state.current_dialog_line = 0;
-                state.current_dialog = Some(dialog_asset.clone());
+    if let Ok(state) = query.get_single() {
+        if state.paused && state.settings_open {
+            // widgets edit a copy, the change goes out as an intent
+            let mut edited = settings.clone();
+            egui::Window::new("Settings")
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .resizable(false)
+            .collapsible(false)
+            .show(contexts.ctx_mut(), |ui| {
+                ui.heading("Camera");
+                ui.add(egui::Slider::new(&mut edited.camera.sensitivity, 0.1..=5.0).text("Sensitivity"));
+                ui.add(egui::Slider::new(&mut edited.camera.smoothing, 0.0..=0.9).text("Smoothing"));
+                ui.checkbox(&mut edited.camera.invert_y, "Invert Y");
+                ui.horizontal(|ui| {
+                    ui.label("View");
+                    ui.radio_value(&mut edited.camera.mode, CameraMode::ThirdPerson, "Third person");
+                    ui.radio_value(&mut edited.camera.mode, CameraMode::FirstPerson, "First person");
+                });
+
+                ui.add_space(10.0);
+                if ui.button("Back").clicked() {
+                    event_writer.send(GameEvent::ToggleSettings);
+                }
+            });
+
+            if edited != *settings {
+                settings_writer.send(ChangeSettings(edited));
             }
         }
+    }
+}
 
+fn render_dialog_box(
+    mut contexts: EguiContexts,
+    game_state: Query<&GameState>,
+    name_query: Query<&Name>,
+) {
+    if let Ok(state) = game_state.get_single() {
         if state.in_dialog {
             if let Some(dialog) = &state.current_dialog {
+                // unnamed speakers (trigger volumes, props) get no name line
+                let speaker_name = state
+                    .dialog_speaker
+                    .and_then(|speaker| name_query.get(speaker).ok())
+                    .map(|name| name.to_string())
+                    .unwrap_or_default();
+
                 egui::Window::new("Dialog")
                     .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -30.0])
                     .resizable(false)
@@ -121,7 +323,7 @@ fn render_dialog_box(
                     .fixed_size([600.0, 150.0])
                     .show(contexts.ctx_mut(), |ui| {
                         ui.vertical_centered(|ui| {
-                            ui.label("Character Name");
+                            ui.label(RichText::new(&speaker_name).strong());
                             ui.add_space(10.0);
                             ui.label(RichText::new(&dialog.lines[state.current_dialog_line])
                                 .size(16.0)
@@ -134,17 +336,7 @@ fn render_dialog_box(
                                     .color(Color32::LIGHT_GRAY));
                         });
                     });
-    
-                if input.just_pressed(KeyCode::Space) || mouse_input.just_pressed(MouseButton::Left) {
-                    if state.current_dialog_line < dialog.lines.len() - 1 {
-                        state.current_dialog_line += 1;
-                    } else {
-                        state.in_dialog = false;
-                        state.current_dialog = None;
-                        state.current_dialog_line = 0;
-                    }
-                }
             }
         }
     }
-}
\ No newline at end of file
+}
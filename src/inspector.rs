@@ -0,0 +1,307 @@
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
+use bevy_rapier3d::geometry::ColliderView;
+use bevy_rapier3d::prelude::*;
+
+use crate::camera_plugin::CameraTransparent;
+use crate::debug_plugin::{draw_collider, DebugOverlays};
+use crate::mechanics::dialog::{DialogAsset, DialogData};
+use crate::mechanics::door::Door;
+use crate::mechanics::inventory::ItemPickup;
+use crate::mechanics::trigger::TriggerState;
+use crate::player_plugin::{Climbable, DialogTrigger, DoorTrigger, Interactable, ItemTrigger, Player};
+
+// entity picked by clicking it or from the list, shown and edited by render_inspector
+#[derive(Resource, Default)]
+pub struct Inspector {
+    pub selected: Option<Entity>,
+    filter: String,
+}
+
+// left click picks whatever collider is under the cursor, or in the middle of the screen while it's locked
+pub fn pick_entity(
+    mut contexts: EguiContexts,
+    mut inspector: ResMut<Inspector>,
+    overlays: Res<DebugOverlays>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    player_query: Query<Entity, With<Player>>,
+    rapier_context: Query<&RapierContext>,
+) {
+    const PICK_DISTANCE: f32 = 100.0;
+
+    if !overlays.inspector || !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform)), Ok(context)) =
+        (windows.get_single(), camera_query.get_single(), rapier_context.get_single())
+    else {
+        return;
+    };
+
+    let position = if window.cursor_options.visible {
+        // clicks on the debug windows themselves
+        if contexts.ctx_mut().is_pointer_over_area() {
+            return;
+        }
+        window.cursor_position()
+    } else {
+        Some(window.size() / 2.0)
+    };
+    let Some(ray) = position.and_then(|position| camera.viewport_to_world(camera_transform, position).ok()) else {
+        return;
+    };
+
+    // the camera usually sits behind the player, pick them from the list instead
+    let mut filter = QueryFilter::default();
+    if let Ok(player) = player_query.get_single() {
+        filter = filter.exclude_collider(player);
+    }
+    // sensors are only clickable while they're drawn, otherwise the volume you're standing in gets in the way
+    if !overlays.triggers {
+        filter = filter.exclude_sensors();
+    }
+
+    if let Some((entity, _)) = context.cast_ray(ray.origin, *ray.direction, PICK_DISTANCE, true, filter) {
+        inspector.selected = Some(entity);
+    }
+}
+
+pub fn render_inspector(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    mut inspector: ResMut<Inspector>,
+    overlays: Res<DebugOverlays>,
+    list_query: Query<(Entity, Option<&Name>), Or<(With<Collider>, With<RigidBody>, With<Interactable>)>>,
+    name_query: Query<&Name>,
+    mut transform_query: Query<(&mut Transform, &GlobalTransform, Option<&Parent>)>,
+    physics_query: Query<(Option<&Collider>, Option<&RigidBody>, Option<&Velocity>)>,
+    marker_query: Query<(
+        Has<Interactable>,
+        Has<DialogTrigger>,
+        Has<DoorTrigger>,
+        Has<ItemTrigger>,
+        Has<Climbable>,
+        Has<CameraTransparent>,
+        Has<Sensor>,
+        Has<ColliderDisabled>,
+    )>,
+    gameplay_query: Query<(Option<&DialogData>, Option<&TriggerState>, Option<&ItemPickup>, Option<&Door>)>,
+    asset_server: Res<AssetServer>,
+    dialogs: Res<Assets<DialogAsset>>,
+) {
+    if !overlays.inspector {
+        return;
+    }
+    // forget entities that went away with their room
+    if inspector.selected.is_some_and(|entity| !transform_query.contains(entity)) {
+        inspector.selected = None;
+    }
+
+    egui::Window::new("Inspector")
+        .anchor(egui::Align2::LEFT_TOP, [0.0, 0.0])
+        .resizable(false)
+        .collapsible(false)
+        .default_width(320.0)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Filter");
+                ui.text_edit_singleline(&mut inspector.filter);
+            });
+
+            let filter = inspector.filter.to_lowercase();
+            let mut entities: Vec<(Entity, String)> = list_query
+                .iter()
+                .map(|(entity, name)| (entity, entity_label(entity, name)))
+                .filter(|(_, label)| label.to_lowercase().contains(&filter))
+                .collect();
+            entities.sort_by(|a, b| a.1.cmp(&b.1));
+
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+                for (entity, label) in &entities {
+                    if ui.selectable_label(inspector.selected == Some(*entity), label).clicked() {
+                        inspector.selected = Some(*entity);
+                    }
+                }
+            });
+            ui.separator();
+
+            let Some(entity) = inspector.selected else {
+                ui.label("Click something in the world or pick it above");
+                return;
+            };
+            let Ok((mut transform, global_transform, parent)) = transform_query.get_mut(entity) else {
+                return;
+            };
+
+            ui.heading(entity_label(entity, name_query.get(entity).ok()));
+            if let Some(parent) = parent {
+                let label = entity_label(parent.get(), name_query.get(parent.get()).ok());
+                if ui.button(format!("Parent: {}", label)).clicked() {
+                    inspector.selected = Some(parent.get());
+                }
+            }
+
+            // edits go through a copy so the transform is only marked changed when something was dragged
+            ui.add_space(5.0);
+            ui.label(egui::RichText::new("Transform").strong());
+            let mut edited = *transform;
+            let (yaw, pitch, roll) = edited.rotation.to_euler(EulerRot::YXZ);
+            let mut rotation = Vec3::new(pitch.to_degrees(), yaw.to_degrees(), roll.to_degrees());
+            let mut changed = vec3_row(ui, "Position", &mut edited.translation, 0.05);
+            if vec3_row(ui, "Rotation", &mut rotation, 1.0) {
+                // same order as room files, yaw then pitch then roll
+                edited.rotation = Quat::from_euler(
+                    EulerRot::YXZ,
+                    rotation.y.to_radians(),
+                    rotation.x.to_radians(),
+                    rotation.z.to_radians(),
+                );
+                changed = true;
+            }
+            changed |= vec3_row(ui, "Scale", &mut edited.scale, 0.01);
+            if changed {
+                *transform = edited;
+            }
+            ui.label(format!("World position: {:.2}", global_transform.translation()));
+
+            if let Ok((collider, body, velocity)) = physics_query.get(entity) {
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Physics").strong());
+                ui.label(format!("Collider: {}", collider.map_or("None".to_string(), describe_collider)));
+                ui.label(format!("Rigid body: {}", body.map_or("None".to_string(), |body| format!("{:?}", body))));
+                if let Some(velocity) = velocity {
+                    ui.label(format!("Velocity: {:.2}", velocity.linvel));
+                    ui.label(format!("Angular velocity: {:.2}", velocity.angvel));
+                }
+            }
+
+            if let Ok((interactable, dialog, door, item, climbable, camera_transparent, sensor, disabled)) =
+                marker_query.get(entity)
+            {
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("Markers").strong());
+                marker_checkbox(ui, &mut commands, entity, interactable, "Interactable", Interactable);
+                marker_checkbox(ui, &mut commands, entity, dialog, "Dialog trigger", DialogTrigger);
+                marker_checkbox(ui, &mut commands, entity, door, "Door trigger", DoorTrigger);
+                marker_checkbox(ui, &mut commands, entity, item, "Item trigger", ItemTrigger);
+                marker_checkbox(ui, &mut commands, entity, climbable, "Climbable", Climbable);
+                marker_checkbox(ui, &mut commands, entity, camera_transparent, "Camera transparent", CameraTransparent);
+                marker_checkbox(ui, &mut commands, entity, sensor, "Sensor", Sensor);
+                marker_checkbox(ui, &mut commands, entity, disabled, "Collider disabled", ColliderDisabled);
+            }
+
+            if let Ok((dialog_data, trigger, pickup, door)) = gameplay_query.get(entity) {
+                if dialog_data.is_some() || trigger.is_some() || pickup.is_some() || door.is_some() {
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new("Gameplay").strong());
+                }
+                if let Some(dialog_data) = dialog_data {
+                    let handle = &dialog_data.dialog_file;
+                    let path = handle.path().map_or("Not from a file".to_string(), |path| path.to_string());
+                    let load_state = match asset_server.get_load_state(handle) {
+                        Some(LoadState::Failed(err)) => format!("Failed: {}", err),
+                        Some(state) => format!("{:?}", state),
+                        None => "Not tracked".to_string(),
+                    };
+                    ui.label(format!("Dialog: {}", path));
+                    ui.label(format!("Load state: {}", load_state));
+                    if let Some(dialog) = dialogs.get(handle) {
+                        ui.label(format!("Lines: {}", dialog.lines.len()));
+                    }
+                }
+                if let Some(trigger) = trigger {
+                    ui.label(format!("Trigger occupants: {}, fired: {}", trigger.occupants.len(), trigger.fired));
+                }
+                if let Some(pickup) = pickup {
+                    ui.label(format!("Item: {} x{} ({})", pickup.item, pickup.count, pickup.key));
+                }
+                if let Some(door) = door {
+                    ui.label(format!("Door to: {} at {}", door.room, door.spawn_point));
+                }
+            }
+        });
+}
+
+// outline and axes on the selected entity
+pub fn draw_selection(
+    mut gizmos: Gizmos,
+    inspector: Res<Inspector>,
+    overlays: Res<DebugOverlays>,
+    selected_query: Query<(&GlobalTransform, Option<&Collider>)>,
+) {
+    const SELECTED_COLOR: Color = Color::srgb(1.0, 0.4, 1.0);
+
+    if !overlays.inspector {
+        return;
+    }
+    let Some(Ok((global_transform, collider))) = inspector.selected.map(|entity| selected_query.get(entity)) else {
+        return;
+    };
+
+    gizmos.axes(*global_transform, 1.0);
+    if let Some(collider) = collider {
+        draw_collider(&mut gizmos, collider, global_transform, SELECTED_COLOR);
+    }
+}
+
+fn entity_label(entity: Entity, name: Option<&Name>) -> String {
+    match name {
+        Some(name) => format!("{} ({})", name, entity),
+        None => format!("Entity {}", entity),
+    }
+}
+
+fn describe_collider(collider: &Collider) -> String {
+    match collider.as_typed_shape() {
+        ColliderView::Ball(ball) => format!("Ball, radius {:.2}", ball.radius()),
+        ColliderView::Cuboid(cuboid) => format!("Cuboid, half extents {:.2}", cuboid.half_extents()),
+        ColliderView::Capsule(capsule) => format!(
+            "Capsule, radius {:.2}, from {:.2} to {:.2}",
+            capsule.radius(),
+            capsule.segment().a(),
+            capsule.segment().b()
+        ),
+        ColliderView::Cylinder(cylinder) => format!(
+            "Cylinder, radius {:.2}, half height {:.2}",
+            cylinder.radius(),
+            cylinder.half_height()
+        ),
+        ColliderView::Compound(compound) => format!("Compound of {} shapes", compound.shapes().len()),
+        _ => format!("{:?}", collider.raw.shape_type()),
+    }
+}
+
+// drag values for x, y and z on one line, true if any of them moved
+fn vec3_row(ui: &mut egui::Ui, label: &str, value: &mut Vec3, speed: f32) -> bool {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let x = ui.add(egui::DragValue::new(&mut value.x).speed(speed).prefix("x ")).changed();
+        let y = ui.add(egui::DragValue::new(&mut value.y).speed(speed).prefix("y ")).changed();
+        let z = ui.add(egui::DragValue::new(&mut value.z).speed(speed).prefix("z ")).changed();
+        x || y || z
+    })
+    .inner
+}
+
+fn marker_checkbox<T: Component>(
+    ui: &mut egui::Ui,
+    commands: &mut Commands,
+    entity: Entity,
+    has: bool,
+    label: &str,
+    marker: T,
+) {
+    let mut checked = has;
+    if ui.checkbox(&mut checked, label).changed() {
+        if checked {
+            commands.entity(entity).insert(marker);
+        } else {
+            commands.entity(entity).remove::<T>();
+        }
+    }
+}
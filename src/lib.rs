@@ -0,0 +1,14 @@
+// game plugins live in the library so the headless tests in tests/ can build an App from them
+pub mod player_plugin;
+pub mod camera_plugin;
+pub mod settings_plugin;
+pub mod gui_plugin;
+#[cfg(feature = "debug")]
+pub mod debug_plugin;
+#[cfg(feature = "debug")]
+pub mod replay_plugin;
+#[cfg(feature = "debug")]
+pub mod console;
+#[cfg(feature = "debug")]
+pub mod inspector;
+pub mod mechanics;
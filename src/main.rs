@@ -2,48 +2,68 @@ use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use bevy_egui::EguiPlugin;
 
-mod player_plugin;
-mod gui_plugin;
-mod debug_plugin;
-mod mechanics;
-
-use gui_plugin::GuiPlugin;
-use debug_plugin::DebugPlugin;
-use player_plugin::{DialogTrigger, Interactable, PlayerPlugin};
-use mechanics::dialog::{DialogData, DialogEntity, DialogPlugin};
+use room_world::gui_plugin::{GuiPlugin, GuiRenderPlugin};
+use room_world::camera_plugin::CameraPlugin;
+use room_world::settings_plugin::SettingsPlugin;
+#[cfg(feature = "debug")]
+use room_world::debug_plugin::DebugPlugin;
+use room_world::player_plugin::PlayerPlugin;
+use room_world::mechanics::dialog::DialogPlugin;
+use room_world::mechanics::door::DoorPlugin;
+use room_world::mechanics::flags::FlagsPlugin;
+use room_world::mechanics::gltf_level::GltfLevelPlugin;
+use room_world::mechanics::inventory::{InventoryPlugin, InventoryRenderPlugin};
+use room_world::mechanics::navigation::NavigationPlugin;
+use room_world::mechanics::npc::NpcPlugin;
+use room_world::mechanics::platform::PlatformPlugin;
+use room_world::mechanics::quest::{QuestPlugin, QuestRenderPlugin};
+use room_world::mechanics::room::{LoadRoom, RoomPlugin};
+use room_world::mechanics::save::SavePlugin;
+use room_world::mechanics::trigger::TriggerPlugin;
+use room_world::mechanics::visuals::VisualsPlugin;
 
 fn main() {
-    App::new()
+    let mut app = App::new();
+    app
         // outside plugins
         .add_plugins(DefaultPlugins)
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
-        .add_plugins(RapierDebugRenderPlugin::default())
         .add_plugins(EguiPlugin)
         
         // personal plugins
+        .add_plugins(SettingsPlugin)
         .add_plugins(GuiPlugin)
+        .add_plugins(GuiRenderPlugin)
         .add_plugins(PlayerPlugin)
+        .add_plugins(CameraPlugin)
         .add_plugins(DialogPlugin)
-        .add_plugins(DebugPlugin)
-        .add_systems(Startup, setup_scene)
-        .run();
-}
+        .add_plugins(PlatformPlugin)
+        .add_plugins(RoomPlugin)
+        .add_plugins(DoorPlugin)
+        .add_plugins(GltfLevelPlugin)
+        .add_plugins(SavePlugin)
+        .add_plugins(FlagsPlugin)
+        .add_plugins(TriggerPlugin)
+        .add_plugins(VisualsPlugin)
+        .add_plugins(NavigationPlugin)
+        .add_plugins(NpcPlugin)
+        .add_plugins(InventoryPlugin)
+        .add_plugins(InventoryRenderPlugin)
+        .add_plugins(QuestPlugin)
+        .add_plugins(QuestRenderPlugin)
+        .add_systems(Startup, setup_scene);
 
-fn setup_scene(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // ground
-    commands
-        .spawn(Collider::cuboid(25.0, 0.1, 25.0))
-        .insert(Transform::from_xyz(0.0, -2.0, 0.0))
-        .insert(RigidBody::Fixed);
+    // cargo run --features debug
+    #[cfg(feature = "debug")]
+    app.add_plugins(RapierDebugRenderPlugin::default().disabled())
+        .add_plugins(DebugPlugin);
 
-    commands.spawn((
-        Collider::ball(1.0),
-        Transform::from_xyz(5.0, 0.5, 0.0),
-        Interactable,
-        DialogTrigger,
-        DialogEntity,
-        DialogData {
-            dialog_file: asset_server.load("dialog_test.ron"),
-        }
-    ));
-}
\ No newline at end of file
+    app.run();
+}
+
+fn setup_scene(mut event_writer: EventWriter<LoadRoom>) {
+    event_writer.send(LoadRoom {
+        room: "start".to_string(),
+        spawn_point: None,
+    });
+}
@@ -9,7 +9,7 @@ pub struct DialogPlugin;
 impl Plugin for DialogPlugin {
     fn build(&self, app: &mut App) {
         app.init_asset::<DialogAsset>()
-            .add_plugins(RonAssetPlugin::<DialogAsset>::new(&["dialog_test.ron"]))
+            .add_plugins(RonAssetPlugin::<DialogAsset>::new(&["dialog.ron"]))
             .add_event::<DialogEvent>()
             .add_systems(Update, fetch_dialog_data);
     }
@@ -18,11 +18,26 @@ impl Plugin for DialogPlugin {
 #[derive(Asset, TypePath, Debug, Deserialize, Clone)]
 pub struct DialogAsset {
     pub lines: Vec<String>,
+    // camera framing per line, lines past the end reuse the last shot
+    #[serde(default)]
+    pub shots: Vec<DialogShot>,
 }
 
-#[derive(Event)]
+impl DialogAsset {
+    pub fn shot_for_line(&self, line: usize) -> Option<DialogShot> {
+        self.shots.get(line).or(self.shots.last()).copied()
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum DialogShot {
+    OverShoulder,
+    TwoShot,
+}
+
+#[derive(Event, Clone)]
 pub enum DialogEvent {
-    DialogData(DialogAsset),
+    DialogData(DialogAsset, Entity), // dialog and the entity speaking it
 }
 
 // marker
@@ -34,6 +49,10 @@ pub struct DialogData {
     pub dialog_file: Handle<DialogAsset>,
 }
 
+// default framing for a speaker when their dialog doesn't specify shots
+#[derive(Component)]
+pub struct DialogCamera(pub DialogShot);
+
 fn fetch_dialog_data(
     mut dialog_event: EventReader<InteractType>,
     mut event_writer: EventWriter<DialogEvent>,
@@ -44,7 +63,7 @@ fn fetch_dialog_data(
         if let InteractType::Dialog(entity) = event {
             if let Ok((dialog_data, _)) = dialog_query.get(*entity) {
                 if let Some(asset) = dialog_assets.get(&dialog_data.dialog_file) {
-                    event_writer.send(DialogEvent::DialogData(asset.clone()));
+                    event_writer.send(DialogEvent::DialogData(asset.clone(), *entity));
                 }
             }
         }
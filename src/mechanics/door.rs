@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::gui_plugin::{GameState, GameStateMarker};
+use crate::mechanics::room::{CurrentRoom, LoadRoom, RoomLoading};
+use crate::player_plugin::{InteractType, Player};
+
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RoomTransition>()
+            .add_systems(Startup, spawn_fade_overlay)
+            .add_systems(Update, (
+                handle_door_interaction,
+                check_portals,
+                update_transition,
+                update_fade_overlay,
+            ).chain().before(RoomLoading));
+    }
+}
+
+// leads to a spawn point in another room, used by both doors (press E) and portals (walk in)
+#[derive(Component, Clone, PartialEq, Debug)]
+pub struct Door {
+    pub room: String,
+    pub spawn_point: String,
+}
+
+// marker for door volumes that trigger on contact instead of interaction
+#[derive(Component)]
+pub struct Portal;
+
+#[derive(Resource)]
+pub struct RoomTransition {
+    pub phase: TransitionPhase,
+    pub fade: f32, // 0.0 clear, 1.0 fully black
+    portals_armed: bool, // false until the player has stepped out of every portal after arriving
+}
+
+impl Default for RoomTransition {
+    fn default() -> Self {
+        Self {
+            phase: TransitionPhase::Idle,
+            fade: 0.0,
+            portals_armed: true,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TransitionPhase {
+    Idle,
+    FadingOut(Door),
+    Loading(String),
+    FadingIn,
+}
+
+#[derive(Component)]
+struct FadeOverlay;
+
+fn spawn_fade_overlay(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.0)),
+        GlobalZIndex(100),
+        FadeOverlay,
+    ));
+}
+
+fn begin_transition(transition: &mut RoomTransition, state: &mut GameState, door: &Door) {
+    if transition.phase != TransitionPhase::Idle {
+        return;
+    }
+    info!("Transition to {} at {}", door.room, door.spawn_point);
+    transition.phase = TransitionPhase::FadingOut(door.clone());
+    state.in_transition = true;
+}
+
+fn handle_door_interaction(
+    mut event_reader: EventReader<InteractType>,
+    mut transition: ResMut<RoomTransition>,
+    mut game_query: Query<&mut GameState, With<GameStateMarker>>,
+    door_query: Query<&Door>,
+) {
+    for event in event_reader.read() {
+        if let InteractType::Door(entity) = event {
+            if let (Ok(door), Ok(mut state)) = (door_query.get(*entity), game_query.get_single_mut()) {
+                begin_transition(&mut transition, &mut state, door);
+            }
+        }
+    }
+}
+
+fn check_portals(
+    mut transition: ResMut<RoomTransition>,
+    mut game_query: Query<&mut GameState, With<GameStateMarker>>,
+    player_query: Query<Entity, With<Player>>,
+    portal_query: Query<(Entity, &Door), With<Portal>>,
+    rapier_context: Query<&RapierContext>,
+) {
+    let (Ok(player), Ok(context)) = (player_query.get_single(), rapier_context.get_single()) else {
+        return;
+    };
+
+    let touching = portal_query
+        .iter()
+        .find(|(portal, _)| context.intersection_pair(player, *portal) == Some(true));
+
+    match touching {
+        Some((_, door)) if transition.portals_armed => {
+            if let Ok(mut state) = game_query.get_single_mut() {
+                if state.in_gameplay() {
+                    transition.portals_armed = false;
+                    begin_transition(&mut transition, &mut state, door);
+                }
+            }
+        }
+        Some(_) => {}
+        None => {
+            if transition.phase == TransitionPhase::Idle {
+                transition.portals_armed = true;
+            }
+        }
+    }
+}
+
+fn update_transition(
+    mut transition: ResMut<RoomTransition>,
+    mut load_writer: EventWriter<LoadRoom>,
+    mut game_query: Query<&mut GameState, With<GameStateMarker>>,
+    current_room: Res<CurrentRoom>,
+    time: Res<Time>,
+) {
+    const FADE_TIME: f32 = 0.4;
+
+    let step = time.delta_secs() / FADE_TIME;
+    match transition.phase.clone() {
+        TransitionPhase::Idle => {}
+        TransitionPhase::FadingOut(door) => {
+            transition.fade = (transition.fade + step).min(1.0);
+            if transition.fade >= 1.0 {
+                load_writer.send(LoadRoom {
+                    room: door.room.clone(),
+                    spawn_point: Some(door.spawn_point.clone()),
+                });
+                transition.phase = TransitionPhase::Loading(door.room);
+            }
+        }
+        TransitionPhase::Loading(room) => {
+            // stay black until the new room has actually been spawned (or failed to load)
+            if current_room.name.as_deref() == Some(room.as_str()) || !current_room.is_loading() {
+                transition.phase = TransitionPhase::FadingIn;
+                transition.portals_armed = false;
+            }
+        }
+        TransitionPhase::FadingIn => {
+            transition.fade = (transition.fade - step).max(0.0);
+            if transition.fade <= 0.0 {
+                transition.phase = TransitionPhase::Idle;
+                if let Ok(mut state) = game_query.get_single_mut() {
+                    state.in_transition = false;
+                }
+            }
+        }
+    }
+}
+
+fn update_fade_overlay(
+    transition: Res<RoomTransition>,
+    mut overlay_query: Query<&mut BackgroundColor, With<FadeOverlay>>,
+) {
+    if !transition.is_changed() {
+        return;
+    }
+    if let Ok(mut background) = overlay_query.get_single_mut() {
+        background.0 = Color::BLACK.with_alpha(transition.fade);
+    }
+}
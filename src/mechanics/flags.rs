@@ -0,0 +1,41 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+
+pub struct FlagsPlugin;
+
+impl Plugin for FlagsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StoryFlags>()
+            .add_event::<FlagChanged>();
+    }
+}
+
+// named story switches ("met_guide", "hallway_lights_on"), saved with the game
+#[derive(Resource, Default, Clone, Debug)]
+pub struct StoryFlags(pub BTreeSet<String>);
+
+impl StoryFlags {
+    pub fn is_set(&self, flag: &str) -> bool {
+        self.0.contains(flag)
+    }
+}
+
+#[derive(Event)]
+pub struct FlagChanged {
+    pub flag: String,
+    pub set: bool,
+}
+
+// flags should be changed through here so listeners (quests, schedules) hear about it
+pub fn set_flag(flags: &mut StoryFlags, writer: &mut EventWriter<FlagChanged>, flag: &str, set: bool) {
+    let changed = if set {
+        flags.0.insert(flag.to_string())
+    } else {
+        flags.0.remove(flag)
+    };
+    if changed {
+        debug!("Flag {} = {}", flag, set);
+        writer.send(FlagChanged { flag: flag.to_string(), set });
+    }
+}
@@ -0,0 +1,162 @@
+use bevy::gltf::GltfExtras;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::mechanics::dialog::{DialogData, DialogEntity};
+use crate::mechanics::door::Door;
+use crate::mechanics::room::RoomSpawnPoint;
+use crate::player_plugin::{Climbable, DialogTrigger, DoorTrigger, Interactable};
+
+// turns tagged nodes of glTF scenes (room geometry authored in Blender) into gameplay entities
+pub struct GltfLevelPlugin;
+
+impl Plugin for GltfLevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, process_gltf_nodes);
+    }
+}
+
+// node extras, set as custom properties in Blender (exported with "Include > Custom Properties")
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+struct NodeExtras {
+    collider: Option<ColliderKind>,
+    sensor: bool,
+    climbable: bool,
+    dialog: Option<String>,
+    door: Option<DoorExtras>,
+    spawn_point: Option<String>,
+    yaw: f32,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ColliderKind {
+    Trimesh,
+    Convex,
+}
+
+#[derive(Deserialize, Debug)]
+struct DoorExtras {
+    room: String,
+    spawn_point: String,
+}
+
+// name suffixes, Blender's ".001" duplicate numbering is ignored
+fn collider_from_name(name: &str) -> Option<(ColliderKind, bool)> {
+    let base = name.split('.').next().unwrap_or(name);
+    if base.ends_with("-colonly") {
+        Some((ColliderKind::Trimesh, true))
+    } else if base.ends_with("-col") {
+        Some((ColliderKind::Trimesh, false))
+    } else if base.ends_with("-convex") {
+        Some((ColliderKind::Convex, false))
+    } else {
+        None
+    }
+}
+
+fn process_gltf_nodes(
+    mut commands: Commands,
+    node_query: Query<(Entity, &Name, Option<&GltfExtras>, Option<&Children>), Added<Name>>,
+    mesh_query: Query<&Mesh3d>,
+    meshes: Res<Assets<Mesh>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, name, extras, children) in node_query.iter() {
+        let extras = match extras {
+            Some(extras) => match serde_json::from_str::<NodeExtras>(&extras.value) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    warn!("Ignoring extras on {}: {}", name, err);
+                    NodeExtras::default()
+                }
+            },
+            None => NodeExtras::default(),
+        };
+
+        let from_name = collider_from_name(name.as_str());
+        let interactable = extras.dialog.is_some() || extras.door.is_some();
+        let kind = extras
+            .collider
+            .or(from_name.map(|(kind, _)| kind))
+            // interactables need something to overlap the interaction sphere
+            .or(interactable.then_some(ColliderKind::Convex));
+        let collider_only = from_name.is_some_and(|(_, hidden)| hidden);
+
+        if let Some(spawn_point) = &extras.spawn_point {
+            commands.entity(entity).insert(RoomSpawnPoint {
+                name: spawn_point.clone(),
+                yaw: extras.yaw,
+            });
+        }
+
+        let Some(kind) = kind else {
+            continue;
+        };
+
+        // glTF nodes keep their primitives as children, the node itself may also carry a mesh
+        let targets: Vec<Entity> = std::iter::once(entity)
+            .chain(children.into_iter().flat_map(|children| children.iter().copied()))
+            .filter(|target| mesh_query.contains(*target))
+            .collect();
+
+        for (index, target) in targets.iter().enumerate() {
+            let Ok(mesh_handle) = mesh_query.get(*target) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(&mesh_handle.0) else {
+                warn!("Mesh for {} not loaded, skipping collider", name);
+                continue;
+            };
+
+            let shape = match kind {
+                ColliderKind::Trimesh => ComputedColliderShape::default(),
+                ColliderKind::Convex => ComputedColliderShape::ConvexHull,
+            };
+            let Some(collider) = Collider::from_bevy_mesh(mesh, &shape) else {
+                warn!("Could not build a collider for {}", name);
+                continue;
+            };
+
+            let mut target_commands = commands.entity(*target);
+            target_commands.insert(collider);
+
+            if extras.sensor || extras.climbable {
+                target_commands.insert(Sensor);
+            }
+            if extras.climbable {
+                target_commands.insert(Climbable);
+            }
+            if collider_only {
+                target_commands.insert(Visibility::Hidden);
+            }
+
+            // gameplay markers only go on the first primitive so one node fires one interaction
+            if index > 0 {
+                continue;
+            }
+            if let Some(dialog) = &extras.dialog {
+                target_commands.insert((
+                    Interactable,
+                    DialogTrigger,
+                    DialogEntity,
+                    DialogData {
+                        dialog_file: asset_server.load(dialog.clone()),
+                    },
+                ));
+            }
+            if let Some(door) = &extras.door {
+                target_commands.insert((
+                    Interactable,
+                    DoorTrigger,
+                    Door {
+                        room: door.room.clone(),
+                        spawn_point: door.spawn_point.clone(),
+                    },
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::gui_plugin::{GameEvent, GameState, GameStateMarker};
+use crate::player_plugin::InteractType;
+
+// items the player carries, what quest collect objectives count and item rewards hand out
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inventory>()
+            .add_event::<ItemCollected>()
+            .add_systems(Update, pick_up_items);
+    }
+}
+
+// inventory window and pickup notices, needs EguiPlugin
+pub struct InventoryRenderPlugin;
+
+impl Plugin for InventoryRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (render_inventory, show_pickup_notices.after(pick_up_items)));
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct Inventory {
+    pub items: BTreeMap<String, u32>,
+    pub collected: BTreeSet<String>, // pickups already taken, see pickup_key, so they don't respawn with the room
+}
+
+impl Inventory {
+    pub fn count(&self, item: &str) -> u32 {
+        self.items.get(item).copied().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, item: &str, count: u32) {
+        *self.items.entry(item.to_string()).or_insert(0) += count;
+    }
+}
+
+// item lying in a room, taken with the interact key
+#[derive(Component)]
+pub struct ItemPickup {
+    pub item: String,
+    pub count: u32,
+    pub key: String,
+}
+
+#[derive(Event)]
+pub struct ItemCollected {
+    pub item: String,
+    pub count: u32,
+}
+
+// pickups are identified by their room and position in its interactables list
+pub fn pickup_key(room: &str, index: usize) -> String {
+    format!("{}#{}", room, index)
+}
+
+fn pick_up_items(
+    mut commands: Commands,
+    mut event_reader: EventReader<InteractType>,
+    mut collected_writer: EventWriter<ItemCollected>,
+    mut inventory: ResMut<Inventory>,
+    pickup_query: Query<&ItemPickup>,
+) {
+    for event in event_reader.read() {
+        if let InteractType::Item(entity) = event {
+            if let Ok(pickup) = pickup_query.get(*entity) {
+                debug!("Picked up {} x{}", pickup.item, pickup.count);
+                inventory.add(&pickup.item, pickup.count);
+                inventory.collected.insert(pickup.key.clone());
+                collected_writer.send(ItemCollected {
+                    item: pickup.item.clone(),
+                    count: pickup.count,
+                });
+                commands.entity(*entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn render_inventory(
+    mut contexts: EguiContexts,
+    mut event_writer: EventWriter<GameEvent>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    inventory: Res<Inventory>,
+) {
+    let Ok(state) = game_query.get_single() else {
+        return;
+    };
+    if !state.inventory_open || state.paused {
+        return;
+    }
+
+    egui::Window::new("Inventory")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .resizable(false)
+        .collapsible(false)
+        .fixed_size([300.0, 240.0])
+        .show(contexts.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                if inventory.items.is_empty() {
+                    ui.label("Nothing yet");
+                }
+                for (item, count) in &inventory.items {
+                    ui.label(format!("{} x{}", item, count));
+                }
+            });
+
+            ui.vertical_centered(|ui| {
+                if ui.button("Close").clicked() {
+                    event_writer.send(GameEvent::ToggleInventory);
+                }
+                ui.label(egui::RichText::new("[I] Inventory").size(12.0).color(egui::Color32::LIGHT_GRAY));
+            });
+        });
+}
+
+// short "+1 crystal" lines in the corner after picking something up
+fn show_pickup_notices(
+    mut contexts: EguiContexts,
+    mut event_reader: EventReader<ItemCollected>,
+    mut notices: Local<Vec<(String, f32)>>,
+    time: Res<Time>,
+) {
+    const NOTICE_TIME: f32 = 2.5;
+
+    for event in event_reader.read() {
+        notices.push((format!("+{} {}", event.count, event.item), NOTICE_TIME));
+    }
+    for notice in notices.iter_mut() {
+        notice.1 -= time.delta_secs();
+    }
+    notices.retain(|(_, time_left)| *time_left > 0.0);
+    if notices.is_empty() {
+        return;
+    }
+
+    egui::Area::new(egui::Id::new("pickup_notices"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-20.0, -20.0])
+        .show(contexts.ctx_mut(), |ui| {
+            for (text, _) in notices.iter() {
+                ui.label(egui::RichText::new(text).size(16.0).color(egui::Color32::WHITE));
+            }
+        });
+}
@@ -1 +1,13 @@
-pub mod dialog;
\ No newline at end of file
+pub mod dialog;
+pub mod door;
+pub mod flags;
+pub mod gltf_level;
+pub mod inventory;
+pub mod navigation;
+pub mod npc;
+pub mod platform;
+pub mod quest;
+pub mod room;
+pub mod save;
+pub mod trigger;
+pub mod visuals;
\ No newline at end of file
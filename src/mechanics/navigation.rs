@@ -0,0 +1,545 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::gui_plugin::{GameState, GameStateMarker};
+use crate::mechanics::room::RoomChanged;
+
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavMesh>()
+            .add_systems(Update, (bake_navmesh, move_nav_agents.in_set(NavAgentMovement)).chain());
+    }
+}
+
+// agents walking their paths, anything steering them (npcs) runs before so facing is decided once a frame
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NavAgentMovement;
+
+const CELL_SIZE: f32 = 0.5;
+const AGENT_RADIUS: f32 = 0.4;
+const AGENT_HEIGHT: f32 = 1.8;
+const MAX_STEP: f32 = 0.45; // largest floor height change between neighbouring cells
+const BAKE_DELAY: f32 = 0.5; // seconds without new static colliders before baking, glTF colliders arrive late
+
+// not a polygon mesh despite the name, a heightfield grid (NavGrid) baked per room
+#[derive(Resource, Default)]
+pub struct NavMesh {
+    pub grid: Option<NavGrid>,
+    bake_in: Option<f32>,
+}
+
+// floor heights an agent can stand on, sampled on a regular grid over the room. One layer only,
+// where surfaces stack the lowest one with head room wins
+#[derive(Clone, Debug)]
+pub struct NavGrid {
+    origin: Vec2, // world xz of the center of cell (0, 0)
+    cell_size: f32,
+    width: usize,
+    depth: usize,
+    heights: Vec<Option<f32>>,
+}
+
+type Cell = (usize, usize);
+
+impl NavGrid {
+    // sample gives the walkable floor height at a world xz, if there is one
+    pub fn bake(min: Vec2, max: Vec2, cell_size: f32, mut sample: impl FnMut(Vec2) -> Option<f32>) -> Self {
+        let width = ((max.x - min.x) / cell_size).ceil().max(1.0) as usize;
+        let depth = ((max.y - min.y) / cell_size).ceil().max(1.0) as usize;
+        let origin = min + Vec2::splat(cell_size * 0.5);
+
+        let mut heights = Vec::with_capacity(width * depth);
+        for z in 0..depth {
+            for x in 0..width {
+                heights.push(sample(origin + Vec2::new(x as f32, z as f32) * cell_size));
+            }
+        }
+
+        Self { origin, cell_size, width, depth, heights }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn walkable_cells(&self) -> impl Iterator<Item = Vec3> + '_ {
+        (0..self.depth).flat_map(move |z| (0..self.width).filter_map(move |x| self.cell_center((x, z))))
+    }
+
+    fn height(&self, (x, z): Cell) -> Option<f32> {
+        if x >= self.width || z >= self.depth {
+            return None;
+        }
+        self.heights[z * self.width + x]
+    }
+
+    fn cell_center(&self, cell: Cell) -> Option<Vec3> {
+        let height = self.height(cell)?;
+        let xz = self.origin + Vec2::new(cell.0 as f32, cell.1 as f32) * self.cell_size;
+        Some(Vec3::new(xz.x, height, xz.y))
+    }
+
+    fn cell_at(&self, position: Vec3) -> Option<Cell> {
+        let local = (Vec2::new(position.x, position.z) - self.origin) / self.cell_size;
+        let (x, z) = (local.x.round(), local.y.round());
+        if x < 0.0 || z < 0.0 || x as usize >= self.width || z as usize >= self.depth {
+            return None;
+        }
+        Some((x as usize, z as usize))
+    }
+
+    // start and goal points can be inside a wall or just off the edge
+    fn nearest_walkable(&self, position: Vec3) -> Option<Cell> {
+        const SEARCH_RADIUS: i32 = 4;
+
+        let local = (Vec2::new(position.x, position.z) - self.origin) / self.cell_size;
+        let (center_x, center_z) = (local.x.round() as i32, local.y.round() as i32);
+
+        let mut nearest = None;
+        let mut nearest_distance = f32::MAX;
+        for z in (center_z - SEARCH_RADIUS)..=(center_z + SEARCH_RADIUS) {
+            for x in (center_x - SEARCH_RADIUS)..=(center_x + SEARCH_RADIUS) {
+                if x < 0 || z < 0 {
+                    continue;
+                }
+                let cell = (x as usize, z as usize);
+                if let Some(center) = self.cell_center(cell) {
+                    let distance = Vec2::new(center.x - position.x, center.z - position.z).length_squared();
+                    if distance < nearest_distance {
+                        nearest_distance = distance;
+                        nearest = Some(cell);
+                    }
+                }
+            }
+        }
+        nearest
+    }
+
+    fn can_step(&self, from: Cell, to: Cell) -> bool {
+        match (self.height(from), self.height(to)) {
+            (Some(from), Some(to)) => (from - to).abs() <= MAX_STEP,
+            _ => false,
+        }
+    }
+
+    fn neighbours(&self, cell: Cell) -> impl Iterator<Item = Cell> + '_ {
+        const OFFSETS: [(i32, i32); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+        OFFSETS.iter().filter_map(move |(dx, dz)| {
+            let (x, z) = (cell.0 as i32 + dx, cell.1 as i32 + dz);
+            if x < 0 || z < 0 {
+                return None;
+            }
+            let next = (x as usize, z as usize);
+            if !self.can_step(cell, next) {
+                return None;
+            }
+            // diagonals can't cut the corner of a wall
+            if *dx != 0 && *dz != 0 && !(self.can_step(cell, (next.0, cell.1)) && self.can_step(cell, (cell.0, next.1))) {
+                return None;
+            }
+            Some(next)
+        })
+    }
+
+    fn distance(&self, from: Cell, to: Cell) -> f32 {
+        match (self.cell_center(from), self.cell_center(to)) {
+            (Some(from), Some(to)) => from.distance(to),
+            _ => f32::MAX,
+        }
+    }
+
+    // smoothed list of points from start to goal, start itself is not included
+    pub fn find_path(&self, start: Vec3, goal: Vec3) -> Option<Vec<Vec3>> {
+        let start_cell = self.nearest_walkable(start)?;
+        let goal_cell = self.nearest_walkable(goal)?;
+        let cells = self.a_star(start_cell, goal_cell)?;
+
+        let mut points: Vec<Vec3> = cells.iter().filter_map(|cell| self.cell_center(*cell)).collect();
+        // end on the exact goal rather than the middle of its cell when it's on the grid
+        if let Some(last) = points.last_mut() {
+            let exact = Vec3::new(goal.x, last.y, goal.z);
+            if self.line_walkable(*last, exact) {
+                *last = exact;
+            }
+        }
+        Some(self.smooth(start, &points))
+    }
+
+    fn a_star(&self, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+        let index = |(x, z): Cell| z * self.width + x;
+        let cell = |index: usize| (index % self.width, index / self.width);
+
+        let mut came_from = vec![usize::MAX; self.heights.len()];
+        let mut cost = vec![f32::INFINITY; self.heights.len()];
+        let mut closed = vec![false; self.heights.len()];
+        let mut open = BinaryHeap::new();
+
+        cost[index(start)] = 0.0;
+        open.push(OpenCell { cell: start, estimate: self.distance(start, goal) });
+
+        while let Some(OpenCell { cell: current, .. }) = open.pop() {
+            if current == goal {
+                let mut path = vec![goal];
+                let mut at = index(goal);
+                while at != index(start) {
+                    at = came_from[at];
+                    path.push(cell(at));
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if closed[index(current)] {
+                continue;
+            }
+            closed[index(current)] = true;
+
+            for next in self.neighbours(current) {
+                let next_cost = cost[index(current)] + self.distance(current, next);
+                if next_cost < cost[index(next)] {
+                    cost[index(next)] = next_cost;
+                    came_from[index(next)] = index(current);
+                    open.push(OpenCell { cell: next, estimate: next_cost + self.distance(next, goal) });
+                }
+            }
+        }
+        None
+    }
+
+    // string pulling, skips every point that can be reached in a straight line
+    fn smooth(&self, start: Vec3, points: &[Vec3]) -> Vec<Vec3> {
+        let mut smoothed = Vec::new();
+        let mut from = start;
+        let mut next = 0;
+        while next < points.len() {
+            let furthest = (next..points.len())
+                .rev()
+                .find(|index| self.line_walkable(from, points[*index]))
+                .unwrap_or(next);
+            smoothed.push(points[furthest]);
+            from = points[furthest];
+            next = furthest + 1;
+        }
+        smoothed
+    }
+
+    fn line_walkable(&self, from: Vec3, to: Vec3) -> bool {
+        let length = Vec2::new(to.x - from.x, to.z - from.z).length();
+        let steps = (length / (self.cell_size * 0.25)).ceil().max(1.0) as usize;
+
+        let mut previous: Option<Cell> = None;
+        for step in 0..=steps {
+            let Some(cell) = self.cell_at(from.lerp(to, step as f32 / steps as f32)) else {
+                return false;
+            };
+            if self.height(cell).is_none() {
+                return false;
+            }
+            if previous.is_some_and(|previous| previous != cell && !self.can_step(previous, cell)) {
+                return false;
+            }
+            previous = Some(cell);
+        }
+        true
+    }
+}
+
+// entry in the A* open list, ordered so the heap pops the lowest estimate first
+struct OpenCell {
+    cell: Cell,
+    estimate: f32,
+}
+
+impl PartialEq for OpenCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for OpenCell {}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+// horizontal velocity towards the next point on the path, dropping points as they are reached
+pub fn follow_path(position: Vec3, path: &mut Vec<Vec3>, speed: f32, dt: f32) -> Vec3 {
+    const REACHED_DISTANCE: f32 = 0.15;
+
+    while let Some(next) = path.first() {
+        let offset = Vec3::new(next.x - position.x, 0.0, next.z - position.z);
+        let distance = offset.length();
+        if distance <= REACHED_DISTANCE {
+            path.remove(0);
+            continue;
+        }
+        // don't overshoot the last point in a single frame
+        return offset / distance * speed.min(distance / dt.max(f32::EPSILON));
+    }
+    Vec3::ZERO
+}
+
+// walks its entity to a destination along the navmesh with a kinematic character controller
+#[derive(Component)]
+pub struct NavAgent {
+    pub speed: f32,
+    destination: Option<Vec3>,
+    path: Vec<Vec3>,
+    needs_path: bool,
+    fall_speed: f32,
+}
+
+impl NavAgent {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            destination: None,
+            path: Vec::new(),
+            needs_path: false,
+            fall_speed: 0.0,
+        }
+    }
+
+    pub fn go_to(&mut self, destination: Vec3) {
+        self.destination = Some(destination);
+        self.path.clear();
+        self.needs_path = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.destination = None;
+        self.path.clear();
+        self.needs_path = false;
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.destination.is_some()
+    }
+
+    // corners still ahead of the agent, the last one is the destination
+    pub fn path(&self) -> &[Vec3] {
+        &self.path
+    }
+}
+
+fn bake_navmesh(
+    mut navmesh: ResMut<NavMesh>,
+    mut room_events: EventReader<RoomChanged>,
+    added_query: Query<(), (Added<Collider>, Without<Sensor>, Without<KinematicCharacterController>)>,
+    mut removed_colliders: RemovedComponents<Collider>,
+    static_query: Query<(&Collider, &GlobalTransform, Option<&RigidBody>), Without<Sensor>>,
+    rapier_context: Query<&RapierContext>,
+    time: Res<Time>,
+) {
+    const MAX_LAYERS: usize = 8;
+
+    // picked up items and other despawns can open up cells as well as block them
+    let removed = removed_colliders.read().count() > 0;
+    if room_events.read().count() > 0 {
+        navmesh.grid = None;
+        navmesh.bake_in = Some(BAKE_DELAY);
+    } else if !added_query.is_empty() || removed {
+        navmesh.bake_in = Some(BAKE_DELAY);
+    }
+
+    let Some(bake_in) = navmesh.bake_in else {
+        return;
+    };
+    if bake_in > 0.0 {
+        navmesh.bake_in = Some(bake_in - time.delta_secs());
+        return;
+    }
+    navmesh.bake_in = None;
+
+    let Ok(context) = rapier_context.get_single() else {
+        return;
+    };
+
+    // bounds of everything that doesn't move
+    let mut min = Vec3::MAX;
+    let mut max = Vec3::MIN;
+    for (collider, transform, body) in static_query.iter() {
+        if body.is_some_and(|body| *body != RigidBody::Fixed) {
+            continue;
+        }
+        let aabb = collider.raw.compute_local_aabb();
+        for x in [aabb.mins.x, aabb.maxs.x] {
+            for y in [aabb.mins.y, aabb.maxs.y] {
+                for z in [aabb.mins.z, aabb.maxs.z] {
+                    let corner = transform.transform_point(Vec3::new(x, y, z));
+                    min = min.min(corner);
+                    max = max.max(corner);
+                }
+            }
+        }
+    }
+    if min.x > max.x {
+        return;
+    }
+
+    let filter = QueryFilter::only_fixed().exclude_sensors();
+    // the body above a floor, starting one step up so bumps and slopes don't count as blocking
+    let clearance_half = (AGENT_HEIGHT - MAX_STEP) * 0.5;
+    let body = Collider::capsule_y(clearance_half - AGENT_RADIUS, AGENT_RADIUS);
+
+    let grid = NavGrid::bake(Vec2::new(min.x, min.z), Vec2::new(max.x, max.z), CELL_SIZE, |point| {
+        let mut origin = Vec3::new(point.x, max.y + 1.0, point.y);
+        let mut floor = None;
+
+        // go down through every surface in the column, keeping the lowest one with room to stand.
+        // Rays are hollow so from inside a shape they hit its underside, which faces down and is skipped
+        for _ in 0..MAX_LAYERS {
+            let Some((_, hit)) = context.cast_ray_and_get_normal(origin, Vec3::NEG_Y, origin.y - min.y + 1.0, false, filter) else {
+                break;
+            };
+            if hit.normal.y > 0.7 {
+                let mut blocked = false;
+                context.intersections_with_shape(
+                    hit.point + Vec3::Y * (MAX_STEP + clearance_half),
+                    Quat::IDENTITY,
+                    &body,
+                    filter,
+                    |_| {
+                        blocked = true;
+                        false
+                    },
+                );
+                if !blocked {
+                    floor = Some(hit.point.y);
+                }
+            }
+            origin.y = hit.point.y - 0.01;
+        }
+        floor
+    });
+
+    info!("Baked navmesh: {} walkable cells", grid.walkable_cells().count());
+    navmesh.grid = Some(grid);
+}
+
+fn move_nav_agents(
+    mut agent_query: Query<(
+        &mut NavAgent,
+        &mut KinematicCharacterController,
+        Option<&KinematicCharacterControllerOutput>,
+        &mut Transform,
+    )>,
+    navmesh: Res<NavMesh>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    time: Res<Time>,
+) {
+    const GRAVITY: f32 = -20.0;
+    const GROUNDED_FALL_SPEED: f32 = -1.0; // keeps agents pressed onto slopes and steps down
+    const TURN_SPEED: f32 = 10.0;
+
+    if let Ok(game_state) = game_query.get_single() {
+        if game_state.paused {
+            return;
+        }
+    }
+    let dt = time.delta_secs();
+
+    for (mut agent, mut controller, output, mut transform) in agent_query.iter_mut() {
+        if agent.needs_path {
+            agent.needs_path = false;
+            if let Some(destination) = agent.destination {
+                agent.path = match &navmesh.grid {
+                    Some(grid) => grid.find_path(transform.translation, destination).unwrap_or_else(|| {
+                        warn!("No path to {}", destination);
+                        Vec::new()
+                    }),
+                    // nothing baked yet, head straight there
+                    None => vec![destination],
+                };
+            }
+        }
+
+        let speed = agent.speed;
+        let velocity = follow_path(transform.translation, &mut agent.path, speed, dt);
+        if agent.path.is_empty() {
+            agent.destination = None;
+        }
+
+        agent.fall_speed = if output.is_some_and(|output| output.grounded) {
+            GROUNDED_FALL_SPEED
+        } else {
+            agent.fall_speed + GRAVITY * dt
+        };
+        controller.translation = Some((velocity + Vec3::Y * agent.fall_speed) * dt);
+
+        if velocity.length_squared() > 0.01 {
+            let target_rotation = Transform::default().looking_to(velocity, Vec3::Y).rotation;
+            transform.rotation = transform.rotation.slerp(target_rotation, 1.0 - (-TURN_SPEED * dt).exp());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10x10 m floor at y 0 with a wall across the middle, open at the +x end
+    fn walled_grid() -> NavGrid {
+        NavGrid::bake(Vec2::new(-5.0, -5.0), Vec2::new(5.0, 5.0), CELL_SIZE, |point| {
+            let in_wall = point.y.abs() < 0.5 && point.x < 3.0;
+            (!in_wall).then_some(0.0)
+        })
+    }
+
+    #[test]
+    fn path_goes_around_wall() {
+        let grid = walled_grid();
+        let path = grid.find_path(Vec3::new(-4.0, 0.0, -4.0), Vec3::new(-4.0, 0.0, 4.0)).expect("no path");
+
+        assert!(path.iter().any(|point| point.x >= 3.0), "path cuts through the wall: {:?}", path);
+        assert_eq!(*path.last().unwrap(), Vec3::new(-4.0, 0.0, 4.0));
+    }
+
+    #[test]
+    fn smoothing_leaves_straight_line() {
+        let grid = walled_grid();
+        let path = grid.find_path(Vec3::new(-4.0, 0.0, -4.0), Vec3::new(4.0, 0.0, -4.0)).expect("no path");
+
+        assert_eq!(path, vec![Vec3::new(4.0, 0.0, -4.0)]);
+    }
+
+    #[test]
+    fn no_path_to_enclosed_cell() {
+        let grid = NavGrid::bake(Vec2::new(-5.0, -5.0), Vec2::new(5.0, 5.0), CELL_SIZE, |point| {
+            let ring = point.length() > 2.0 && point.length() < 3.0;
+            (!ring).then_some(0.0)
+        });
+
+        assert!(grid.find_path(Vec3::new(4.5, 0.0, 4.5), Vec3::ZERO).is_none());
+    }
+
+    #[test]
+    fn agent_reaches_point() {
+        let grid = walled_grid();
+        let goal = Vec3::new(-4.0, 0.0, 4.0);
+        let mut path = grid.find_path(Vec3::new(-4.0, 0.0, -4.0), goal).expect("no path");
+        let mut position = Vec3::new(-4.0, 0.0, -4.0);
+
+        // 20 seconds at 60 fps
+        for _ in 0..1200 {
+            position += follow_path(position, &mut path, 3.0, 1.0 / 60.0) / 60.0;
+            assert!(grid.cell_at(position).and_then(|cell| grid.height(cell)).is_some(), "walked off the grid at {}", position);
+        }
+
+        assert!(path.is_empty());
+        assert!(position.distance(goal) < 0.2, "stopped at {}", position);
+    }
+}
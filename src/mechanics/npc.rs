@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::camera_plugin::CameraTransparent;
+use crate::gui_plugin::{GameState, GameStateMarker};
+use crate::mechanics::dialog::{DialogCamera, DialogData, DialogEntity, DialogShot};
+use crate::mechanics::flags::StoryFlags;
+use crate::mechanics::navigation::{NavAgent, NavAgentMovement};
+use crate::mechanics::visuals::{ColliderVisual, RoomMaterial};
+use crate::player_plugin::{DialogTrigger, Interactable, Player};
+
+pub struct NpcPlugin;
+
+impl Plugin for NpcPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (sync_npc_placements, update_npcs).chain().before(NavAgentMovement));
+    }
+}
+
+const NPC_RADIUS: f32 = 0.4;
+const NPC_HALF_HEIGHT: f32 = 0.5; // of the capsule's middle section, 1.8 tall in total
+
+// a character placed in a room file, only present while its schedule matches the story flags
+#[derive(Debug, Deserialize, Clone)]
+pub struct RoomNpc {
+    pub name: String,
+    pub position: [f32; 3], // center of the body
+    #[serde(default)]
+    pub yaw: f32, // degrees
+    #[serde(default)]
+    pub dialog: Option<String>,
+    #[serde(default)]
+    pub shot: Option<DialogShot>,
+    #[serde(default)]
+    pub material: Option<RoomMaterial>,
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    #[serde(default)]
+    pub behaviour: NpcBehaviour,
+    #[serde(default)]
+    pub schedule: NpcSchedule,
+}
+
+fn default_speed() -> f32 {
+    1.5
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub enum NpcBehaviour {
+    #[default]
+    Idle,
+    // strolls to points around where it was placed
+    Wander { radius: f32, pause: f32 },
+    // walks the waypoints in order, looping
+    Patrol { waypoints: Vec<[f32; 3]>, pause: f32 },
+}
+
+// story state the placement is active in. The same name can be placed in several rooms with
+// opposite conditions, e.g. in the start room until "met_guide" and in the hallway after it
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct NpcSchedule {
+    pub requires_flag: Option<String>,
+    pub blocked_by_flag: Option<String>,
+}
+
+impl NpcSchedule {
+    fn is_active(&self, flags: &StoryFlags) -> bool {
+        self.requires_flag.as_ref().is_none_or(|flag| flags.is_set(flag))
+            && self.blocked_by_flag.as_ref().is_none_or(|flag| !flags.is_set(flag))
+    }
+}
+
+// spawned with the room, holds the NPC while its schedule is active
+#[derive(Component)]
+pub struct NpcPlacement {
+    pub npc: RoomNpc,
+    spawned: Option<Entity>,
+}
+
+impl NpcPlacement {
+    pub fn new(npc: RoomNpc) -> Self {
+        Self { npc, spawned: None }
+    }
+}
+
+#[derive(Component)]
+pub struct Npc {
+    behaviour: NpcBehaviour,
+    home: Vec3,
+    walking: bool, // sent the nav agent somewhere, pause once it gets there
+    wait: f32,
+    step: usize,
+}
+
+fn sync_npc_placements(
+    mut commands: Commands,
+    mut placement_query: Query<(Entity, &mut NpcPlacement)>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    flags: Res<StoryFlags>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    let speaker = game_query.get_single().ok().and_then(|state| state.dialog_speaker);
+
+    for (placement_entity, mut placement) in placement_query.iter_mut() {
+        let active = placement.npc.schedule.is_active(&flags);
+
+        match placement.spawned {
+            // don't pull someone out of a conversation, they leave once it's over
+            Some(npc) if !active && speaker != Some(npc) => {
+                commands.entity(npc).despawn_recursive();
+                placement.spawned = None;
+            }
+            None if active => {
+                let npc = spawn_npc(&mut commands, &placement.npc, &mut meshes, &mut materials, &asset_server);
+                commands.entity(placement_entity).add_child(npc);
+                placement.spawned = Some(npc);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn spawn_npc(
+    commands: &mut Commands,
+    npc: &RoomNpc,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    asset_server: &AssetServer,
+) -> Entity {
+    let home = Vec3::from_array(npc.position);
+    let material = match &npc.material {
+        Some(material) => material.to_material(),
+        None => StandardMaterial::from_color(Color::srgb(0.7, 0.55, 0.45)),
+    };
+
+    let mut entity = commands.spawn((
+        Name::new(npc.name.clone()),
+        Npc {
+            behaviour: npc.behaviour.clone(),
+            home,
+            walking: false,
+            wait: 0.0,
+            step: 0,
+        },
+        NavAgent::new(npc.speed),
+        RigidBody::KinematicPositionBased,
+        Collider::capsule_y(NPC_HALF_HEIGHT, NPC_RADIUS),
+        KinematicCharacterController {
+            snap_to_ground: Some(CharacterLength::Absolute(0.5)),
+            ..default()
+        },
+        ColliderVisual(material),
+        CameraTransparent,
+        // rooms and placements sit at the origin, so this is also the world position
+        Transform::from_translation(home).with_rotation(Quat::from_rotation_y(npc.yaw.to_radians())),
+        Visibility::default(),
+    ));
+
+    if let Some(dialog) = &npc.dialog {
+        entity.insert((
+            Interactable,
+            DialogTrigger,
+            DialogEntity,
+            DialogData {
+                dialog_file: asset_server.load(dialog.clone()),
+            },
+        ));
+    }
+    if let Some(shot) = npc.shot {
+        entity.insert(DialogCamera(shot));
+    }
+
+    // eyes, so it's clear which way they are facing
+    entity.with_child((
+        Mesh3d(meshes.add(Cuboid::new(0.45, 0.08, 0.08))),
+        MeshMaterial3d(materials.add(Color::srgb(0.1, 0.1, 0.1))),
+        Transform::from_xyz(0.0, NPC_HALF_HEIGHT * 0.9, -NPC_RADIUS),
+    ));
+
+    entity.id()
+}
+
+// stand-in for random, spreads wander points evenly around home and repeats the same way every run
+fn wander_point(step: usize, radius: f32) -> Vec3 {
+    const GOLDEN_ANGLE: f32 = 2.399_963;
+    const GOLDEN_RATIO_FRACTION: f32 = 0.618_034;
+
+    let n = step as f32 + 1.0; // the first point would otherwise be home
+    let angle = n * GOLDEN_ANGLE;
+    let distance = radius * (n * GOLDEN_RATIO_FRACTION).fract().sqrt();
+    Vec3::new(angle.cos() * distance, 0.0, angle.sin() * distance)
+}
+
+fn update_npcs(
+    mut npc_query: Query<(Entity, &mut Npc, &mut NavAgent, &mut Transform)>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    time: Res<Time>,
+) {
+    const NOTICE_RADIUS: f32 = 3.0; // turns to face the player inside this distance
+    const TURN_SPEED: f32 = 8.0;
+
+    let Ok(state) = game_query.get_single() else {
+        return;
+    };
+    if state.paused {
+        return;
+    }
+    let player = player_query.get_single().ok().map(|transform| transform.translation());
+    let dt = time.delta_secs();
+
+    for (entity, mut npc, mut agent, mut transform) in npc_query.iter_mut() {
+        let to_player = player.map(|player| player - transform.translation).unwrap_or(Vec3::INFINITY);
+        let talking = state.in_dialog && state.dialog_speaker == Some(entity);
+
+        if talking || to_player.length() < NOTICE_RADIUS {
+            agent.stop();
+            let flat = Vec3::new(to_player.x, 0.0, to_player.z);
+            if flat.length_squared() > 0.0001 {
+                let target_rotation = Transform::default().looking_to(flat, Vec3::Y).rotation;
+                transform.rotation = transform.rotation.slerp(target_rotation, 1.0 - (-TURN_SPEED * dt).exp());
+            }
+        } else if npc.wait > 0.0 {
+            npc.wait -= dt;
+        } else if agent.is_moving() {
+            // walking, the agent turns them along the path
+        } else if npc.walking {
+            npc.walking = false;
+            npc.wait = match &npc.behaviour {
+                NpcBehaviour::Wander { pause, .. } | NpcBehaviour::Patrol { pause, .. } => *pause,
+                NpcBehaviour::Idle => 0.0,
+            };
+        } else {
+            let target = match &npc.behaviour {
+                NpcBehaviour::Idle => None,
+                NpcBehaviour::Wander { radius, .. } => Some(npc.home + wander_point(npc.step, *radius)),
+                NpcBehaviour::Patrol { waypoints, .. } if !waypoints.is_empty() => {
+                    Some(Vec3::from_array(waypoints[npc.step % waypoints.len()]))
+                }
+                NpcBehaviour::Patrol { .. } => None,
+            };
+            if let Some(target) = target {
+                agent.go_to(target);
+                npc.walking = true;
+            }
+            npc.step += 1;
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::gui_plugin::{GameState, GameStateMarker};
+use crate::player_plugin::{Player, PlayerMovement, PlayerPhysics};
+
+pub struct PlatformPlugin;
+
+impl Plugin for PlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (
+            (move_elevators, spin_platforms).before(PlayerMovement),
+            carry_riders.after(PlayerMovement),
+        ));
+    }
+}
+
+// velocity of a kinematic platform this frame, read by anything standing on it
+#[derive(Component, Default)]
+pub struct PlatformVelocity {
+    pub linear: Vec3,
+    pub angular: Vec3,
+}
+
+impl PlatformVelocity {
+    // velocity of a point riding the platform, including the tangential part from spinning
+    pub fn point_velocity(&self, center: Vec3, point: Vec3) -> Vec3 {
+        self.linear + self.angular.cross(point - center)
+    }
+}
+
+// moves back and forth between two points, waiting at each end
+#[derive(Component)]
+pub struct Elevator {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub speed: f32,
+    pub pause: f32,
+    progress: f32,
+    rising: bool,
+    wait: f32,
+}
+
+impl Elevator {
+    pub fn new(start: Vec3, end: Vec3, speed: f32, pause: f32) -> Self {
+        Self {
+            start,
+            end,
+            speed,
+            pause,
+            progress: 0.0,
+            rising: true,
+            wait: pause,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Spinner {
+    pub angular_velocity: Vec3,
+}
+
+// attached to the player, tracks what it's standing on and momentum kept after leaving it
+#[derive(Component, Default)]
+pub struct PlatformRider {
+    pub platform: Option<Entity>,
+    pub carried_velocity: Vec3, // horizontal only, gravity already acts on the vertical part through PlayerPhysics
+}
+
+fn move_elevators(
+    mut query: Query<(&mut Transform, &mut Elevator, &mut PlatformVelocity)>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    time: Res<Time>,
+) {
+    if let Ok(game_state) = game_query.get_single() {
+        if game_state.paused { return; }
+    }
+
+    let dt = time.delta_secs();
+    for (mut transform, mut elevator, mut velocity) in query.iter_mut() {
+        let length = elevator.start.distance(elevator.end);
+        if length <= f32::EPSILON || dt <= 0.0 {
+            velocity.linear = Vec3::ZERO;
+            continue;
+        }
+
+        if elevator.wait > 0.0 {
+            elevator.wait -= dt;
+            velocity.linear = Vec3::ZERO;
+            continue;
+        }
+
+        let step = elevator.speed * dt / length;
+        if elevator.rising {
+            elevator.progress = (elevator.progress + step).min(1.0);
+        } else {
+            elevator.progress = (elevator.progress - step).max(0.0);
+        }
+
+        if elevator.progress >= 1.0 || elevator.progress <= 0.0 {
+            elevator.rising = !elevator.rising;
+            elevator.wait = elevator.pause;
+        }
+
+        let target = elevator.start.lerp(elevator.end, elevator.progress);
+        velocity.linear = (target - transform.translation) / dt;
+        transform.translation = target;
+    }
+}
+
+fn spin_platforms(
+    mut query: Query<(&mut Transform, &Spinner, &mut PlatformVelocity)>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    time: Res<Time>,
+) {
+    if let Ok(game_state) = game_query.get_single() {
+        if game_state.paused { return; }
+    }
+
+    for (mut transform, spinner, mut velocity) in query.iter_mut() {
+        velocity.angular = spinner.angular_velocity;
+        transform.rotate(Quat::from_scaled_axis(spinner.angular_velocity * time.delta_secs()));
+    }
+}
+
+fn carry_riders(
+    mut player_query: Query<(
+        Entity,
+        &Transform,
+        &mut KinematicCharacterController,
+        &KinematicCharacterControllerOutput,
+        &mut PlayerPhysics,
+        &mut PlatformRider,
+    ), With<Player>>,
+    platform_query: Query<(&Transform, &PlatformVelocity), Without<Player>>,
+    rapier_context: Query<&RapierContext>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    time: Res<Time>,
+) {
+    const GROUND_PROBE: f32 = 0.6;
+
+    if let Ok(game_state) = game_query.get_single() {
+        if game_state.paused { return; }
+    }
+
+    let (Ok((entity, transform, mut controller, output, mut physics, mut rider)), Ok(context)) =
+        (player_query.get_single_mut(), rapier_context.get_single())
+    else {
+        return;
+    };
+    let dt = time.delta_secs();
+
+    // probe straight down from the feet for whatever we are standing on
+    let ground = if output.grounded {
+        context
+            .cast_ray(
+                transform.translation,
+                -Vec3::Y,
+                GROUND_PROBE,
+                true,
+                QueryFilter::default().exclude_sensors().exclude_collider(entity),
+            )
+            .map(|(ground, _)| ground)
+            .filter(|ground| platform_query.contains(*ground))
+    } else {
+        None
+    };
+
+    if let (Some(previous), None) = (rider.platform, ground) {
+        // just stepped or jumped off, rise or fall with the platform once and keep drifting with it until landing
+        if let Ok((platform_transform, velocity)) = platform_query.get(previous) {
+            let inherited = velocity.point_velocity(platform_transform.translation, transform.translation);
+            physics.velocity.y += inherited.y;
+            rider.carried_velocity = Vec3::new(inherited.x, 0.0, inherited.z);
+        }
+    } else if output.grounded {
+        rider.carried_velocity = Vec3::ZERO;
+    }
+    rider.platform = ground;
+
+    let carry = if let Some(platform) = rider.platform {
+        let Ok((platform_transform, velocity)) = platform_query.get(platform) else {
+            return;
+        };
+        // rotate our offset from the platform center, then add the linear part
+        let offset = transform.translation - platform_transform.translation;
+        let spin = Quat::from_scaled_axis(velocity.angular * dt);
+        (spin * offset - offset) + velocity.linear * dt
+    } else {
+        rider.carried_velocity * dt
+    };
+
+    if carry != Vec3::ZERO {
+        controller.translation = Some(controller.translation.unwrap_or(Vec3::ZERO) + carry);
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::prelude::*;
+use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_egui::{egui::{self, Color32, RichText}, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::gui_plugin::{GameEvent, GameState, GameStateMarker};
+use crate::mechanics::dialog::DialogEvent;
+use crate::mechanics::flags::{set_flag, FlagChanged, StoryFlags};
+use crate::mechanics::inventory::{Inventory, ItemCollected};
+use crate::mechanics::room::{CurrentRoom, RoomChanged};
+
+pub struct QuestPlugin;
+
+impl Plugin for QuestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<QuestBook>()
+            .add_plugins(RonAssetPlugin::<QuestBook>::new(&["quests.ron"]))
+            .init_resource::<QuestLog>()
+            .add_event::<QuestEvent>()
+            .add_event::<TrackQuest>()
+            .add_systems(Startup, load_quest_book)
+            .add_systems(Update, (track_quest_progress, grant_quest_rewards, track_quests).chain());
+    }
+}
+
+// journal window and the tracked quest overlay, needs EguiPlugin
+pub struct QuestRenderPlugin;
+
+impl Plugin for QuestRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (render_journal, render_quest_tracker));
+    }
+}
+
+const QUEST_BOOK_PATH: &str = "quests/main.quests.ron";
+
+// every quest in the game, assets/quests/main.quests.ron
+#[derive(Asset, TypePath, Debug, Deserialize, Clone)]
+pub struct QuestBook {
+    pub quests: Vec<QuestDefinition>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuestDefinition {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub requires_flag: Option<String>, // starts as soon as the game does when None
+    pub stages: Vec<QuestStage>,
+    #[serde(default)]
+    pub rewards: Vec<QuestReward>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuestStage {
+    pub description: String,
+    pub objectives: Vec<Objective>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum Objective {
+    Talk(String), // speaker's Name
+    Collect { item: String, count: u32 },
+    EnterRoom(String),
+    SetFlag(String),
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum QuestReward {
+    Item { item: String, count: u32 },
+    SetFlag(String),
+}
+
+#[derive(Resource)]
+struct QuestBookHandle(Handle<QuestBook>);
+
+// progress on every started quest, saved with the game
+#[derive(Resource, Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct QuestLog {
+    pub quests: BTreeMap<String, QuestProgress>,
+    pub tracked: Option<String>, // shown on the HUD
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct QuestProgress {
+    pub stage: usize,
+    pub done: BTreeSet<usize>, // objectives of the current stage already met
+    pub completed: bool,
+}
+
+#[derive(Event, Debug)]
+pub enum QuestEvent {
+    Started(String),
+    StageCompleted { quest: String, stage: usize },
+    Completed(String),
+}
+
+// quest id the journal wants shown in the tracker
+#[derive(Event, Clone, Debug)]
+pub struct TrackQuest(pub String);
+
+fn load_quest_book(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(QuestBookHandle(asset_server.load(QUEST_BOOK_PATH)));
+}
+
+fn objective_met(
+    objective: &Objective,
+    talked: &[String],
+    inventory: &Inventory,
+    flags: &StoryFlags,
+    current_room: &CurrentRoom,
+) -> bool {
+    match objective {
+        Objective::Talk(name) => talked.contains(name),
+        Objective::Collect { item, count } => inventory.count(item) >= *count,
+        Objective::EnterRoom(room) => current_room.name.as_deref() == Some(room.as_str()),
+        Objective::SetFlag(flag) => flags.is_set(flag),
+    }
+}
+
+fn track_quest_progress(
+    mut dialog_events: EventReader<DialogEvent>,
+    mut room_events: EventReader<RoomChanged>,
+    mut flag_events: EventReader<FlagChanged>,
+    mut item_events: EventReader<ItemCollected>,
+    mut quest_writer: EventWriter<QuestEvent>,
+    mut log: ResMut<QuestLog>,
+    mut checked_book: Local<bool>,
+    name_query: Query<&Name>,
+    book_handle: Res<QuestBookHandle>,
+    books: Res<Assets<QuestBook>>,
+    inventory: Res<Inventory>,
+    flags: Res<StoryFlags>,
+    current_room: Res<CurrentRoom>,
+) {
+    let talked: Vec<String> = dialog_events
+        .read()
+        .filter_map(|event| match event {
+            DialogEvent::DialogData(_, speaker) => name_query.get(*speaker).ok().map(|name| name.to_string()),
+        })
+        .collect();
+
+    // progress only moves when something happens, or when the book or a loaded save first shows up
+    let rooms_changed = room_events.read().count() > 0;
+    let flags_changed = flag_events.read().count() > 0;
+    let items_collected = item_events.read().count() > 0;
+    let something_happened = !talked.is_empty() || rooms_changed || flags_changed || items_collected;
+    let Some(book) = books.get(&book_handle.0) else {
+        return;
+    };
+    if !something_happened && *checked_book && !log.is_changed() {
+        return;
+    }
+    *checked_book = true;
+
+    for quest in &book.quests {
+        if !log.quests.contains_key(&quest.id) {
+            if !quest.requires_flag.as_ref().is_none_or(|flag| flags.is_set(flag)) {
+                continue;
+            }
+            log.quests.insert(quest.id.clone(), QuestProgress::default());
+            if log.tracked.is_none() {
+                log.tracked = Some(quest.id.clone());
+            }
+            quest_writer.send(QuestEvent::Started(quest.id.clone()));
+        }
+
+        let Some(progress) = log.quests.get_mut(&quest.id) else {
+            continue;
+        };
+        // several stages can finish at once, e.g. collecting items before being asked to
+        while !progress.completed {
+            let Some(stage) = quest.stages.get(progress.stage) else {
+                progress.completed = true;
+                quest_writer.send(QuestEvent::Completed(quest.id.clone()));
+                break;
+            };
+            for (index, objective) in stage.objectives.iter().enumerate() {
+                if objective_met(objective, &talked, &inventory, &flags, &current_room) {
+                    progress.done.insert(index);
+                }
+            }
+            if progress.done.len() < stage.objectives.len() {
+                break;
+            }
+            quest_writer.send(QuestEvent::StageCompleted { quest: quest.id.clone(), stage: progress.stage });
+            progress.stage += 1;
+            progress.done.clear();
+        }
+    }
+}
+
+fn grant_quest_rewards(
+    mut quest_events: EventReader<QuestEvent>,
+    mut flag_writer: EventWriter<FlagChanged>,
+    mut item_writer: EventWriter<ItemCollected>,
+    mut flags: ResMut<StoryFlags>,
+    mut inventory: ResMut<Inventory>,
+    mut log: ResMut<QuestLog>,
+    book_handle: Res<QuestBookHandle>,
+    books: Res<Assets<QuestBook>>,
+) {
+    let Some(book) = books.get(&book_handle.0) else {
+        return;
+    };
+
+    for event in quest_events.read() {
+        let id = match event {
+            QuestEvent::Started(id) | QuestEvent::Completed(id) => id,
+            QuestEvent::StageCompleted { quest, .. } => quest,
+        };
+        let Some(quest) = book.quests.iter().find(|quest| &quest.id == id) else {
+            continue;
+        };
+        match event {
+            QuestEvent::Started(_) => {
+                info!("Quest started: {}", quest.title);
+                continue;
+            }
+            QuestEvent::StageCompleted { stage, .. } => {
+                info!("{}: stage {} done", quest.title, stage + 1);
+                continue;
+            }
+            QuestEvent::Completed(_) => info!("Quest completed: {}", quest.title),
+        }
+
+        for reward in &quest.rewards {
+            match reward {
+                QuestReward::Item { item, count } => {
+                    inventory.add(item, *count);
+                    item_writer.send(ItemCollected { item: item.clone(), count: *count });
+                }
+                QuestReward::SetFlag(flag) => set_flag(&mut flags, &mut flag_writer, flag, true),
+            }
+        }
+
+        // move the tracker on to something still in progress
+        if log.tracked.as_ref() == Some(id) {
+            log.tracked = log.quests.iter().find(|(_, progress)| !progress.completed).map(|(id, _)| id.clone());
+        }
+    }
+}
+
+fn track_quests(mut event_reader: EventReader<TrackQuest>, mut log: ResMut<QuestLog>) {
+    for TrackQuest(id) in event_reader.read() {
+        // only quests that are started and not yet done can be tracked
+        if log.quests.get(id).is_some_and(|progress| !progress.completed) {
+            log.tracked = Some(id.clone());
+        }
+    }
+}
+
+fn objective_text(objective: &Objective, done: bool, inventory: &Inventory) -> String {
+    let text = match objective {
+        Objective::Talk(name) => format!("Talk to {}", name),
+        Objective::Collect { item, count } => {
+            format!("Collect {} ({}/{})", item, inventory.count(item).min(*count), count)
+        }
+        Objective::EnterRoom(room) => format!("Go to the {}", room),
+        Objective::SetFlag(flag) => flag.replace('_', " "),
+    };
+    if done {
+        format!("[x] {}", text)
+    } else {
+        format!("[ ] {}", text)
+    }
+}
+
+fn render_journal(
+    mut contexts: EguiContexts,
+    mut event_writer: EventWriter<GameEvent>,
+    mut track_writer: EventWriter<TrackQuest>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    log: Res<QuestLog>,
+    book_handle: Res<QuestBookHandle>,
+    books: Res<Assets<QuestBook>>,
+    inventory: Res<Inventory>,
+) {
+    let Ok(state) = game_query.get_single() else {
+        return;
+    };
+    if !state.journal_open || state.paused {
+        return;
+    }
+    let Some(book) = books.get(&book_handle.0) else {
+        return;
+    };
+
+    let mut track = None;
+    egui::Window::new("Journal")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .resizable(false)
+        .collapsible(false)
+        .fixed_size([420.0, 360.0])
+        .show(contexts.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                if log.quests.is_empty() {
+                    ui.label("No quests yet");
+                }
+                for quest in &book.quests {
+                    let Some(progress) = log.quests.get(&quest.id) else {
+                        continue;
+                    };
+
+                    ui.horizontal(|ui| {
+                        ui.heading(&quest.title);
+                        if progress.completed {
+                            ui.label(RichText::new("Completed").color(Color32::LIGHT_GREEN));
+                        } else if log.tracked.as_ref() != Some(&quest.id) && ui.button("Track").clicked() {
+                            track = Some(quest.id.clone());
+                        }
+                    });
+                    ui.label(&quest.description);
+                    if let Some(stage) = quest.stages.get(progress.stage).filter(|_| !progress.completed) {
+                        ui.add_space(4.0);
+                        ui.label(RichText::new(&stage.description).italics());
+                        for (index, objective) in stage.objectives.iter().enumerate() {
+                            ui.label(objective_text(objective, progress.done.contains(&index), &inventory));
+                        }
+                    }
+                    ui.separator();
+                }
+            });
+
+            ui.vertical_centered(|ui| {
+                if ui.button("Close").clicked() {
+                    event_writer.send(GameEvent::ToggleJournal);
+                }
+                ui.label(RichText::new("[J] Journal").size(12.0).color(Color32::LIGHT_GRAY));
+            });
+        });
+
+    if let Some(id) = track {
+        track_writer.send(TrackQuest(id));
+    }
+}
+
+// current stage of the tracked quest, top left during gameplay
+fn render_quest_tracker(
+    mut contexts: EguiContexts,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    log: Res<QuestLog>,
+    book_handle: Res<QuestBookHandle>,
+    books: Res<Assets<QuestBook>>,
+    inventory: Res<Inventory>,
+) {
+    if let Ok(game_state) = game_query.get_single() {
+        if !game_state.in_gameplay() {
+            return;
+        }
+    }
+    let (Some(book), Some(tracked)) = (books.get(&book_handle.0), &log.tracked) else {
+        return;
+    };
+    let (Some(quest), Some(progress)) = (book.quests.iter().find(|quest| &quest.id == tracked), log.quests.get(tracked)) else {
+        return;
+    };
+    let Some(stage) = quest.stages.get(progress.stage).filter(|_| !progress.completed) else {
+        return;
+    };
+
+    egui::Area::new(egui::Id::new("quest_tracker"))
+        .anchor(egui::Align2::LEFT_TOP, [20.0, 20.0])
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(RichText::new(&quest.title).size(16.0).strong().color(Color32::WHITE));
+            for (index, objective) in stage.objectives.iter().enumerate() {
+                ui.label(RichText::new(objective_text(objective, progress.done.contains(&index), &inventory)).color(Color32::WHITE));
+            }
+        });
+}
@@ -0,0 +1,532 @@
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy::scene::SceneInstance;
+use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::camera_plugin::{CameraOrbit, CameraTransparent};
+use crate::mechanics::dialog::{DialogCamera, DialogData, DialogEntity, DialogShot};
+use crate::mechanics::door::{Door, Portal};
+use crate::mechanics::inventory::{pickup_key, Inventory, ItemPickup};
+use crate::mechanics::npc::{NpcPlacement, RoomNpc};
+use crate::mechanics::platform::{Elevator, PlatformVelocity, Spinner};
+use crate::mechanics::trigger::{insert_trigger, TriggerVolume};
+use crate::mechanics::visuals::{ColliderVisual, RoomMaterial};
+use crate::player_plugin::{Climbable, DialogTrigger, DoorTrigger, Interactable, ItemTrigger, Player, PlayerPhysics};
+
+pub struct RoomPlugin;
+
+impl Plugin for RoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<RoomAsset>()
+            .add_plugins(RonAssetPlugin::<RoomAsset>::new(&["room.ron"]))
+            .add_event::<LoadRoom>()
+            .add_event::<RoomChanged>()
+            .init_resource::<CurrentRoom>()
+            .add_systems(Update, (handle_load_room, spawn_pending_room, place_player).chain().in_set(RoomLoading));
+    }
+}
+
+// room unloading and spawning, for systems that request or wait on room loads
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RoomLoading;
+
+// a room file, assets/rooms/<name>.room.ron
+#[derive(Asset, TypePath, Debug, Deserialize, Clone)]
+pub struct RoomAsset {
+    #[serde(default)]
+    pub geometry: Vec<RoomGeometry>,
+    #[serde(default)]
+    pub interactables: Vec<RoomInteractable>,
+    #[serde(default)]
+    pub portals: Vec<RoomPortal>,
+    #[serde(default)]
+    pub spawn_points: Vec<SpawnPoint>,
+    #[serde(default)]
+    pub lights: Vec<RoomLight>,
+    #[serde(default)]
+    pub triggers: Vec<RoomTrigger>,
+    #[serde(default)]
+    pub ambient: Option<RoomAmbient>,
+    #[serde(default)]
+    pub npcs: Vec<RoomNpc>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RoomGeometry {
+    pub shape: RoomShape,
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub rotation: [f32; 3], // euler angles in degrees
+    #[serde(default)]
+    pub climbable: bool,
+    #[serde(default)]
+    pub camera_transparent: bool,
+    #[serde(default)]
+    pub motion: Option<RoomMotion>,
+    #[serde(default)]
+    pub material: Option<RoomMaterial>, // grey when not given
+    #[serde(default)]
+    pub hidden: bool, // collision only, no mesh
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum RoomShape {
+    Cuboid { half_extents: [f32; 3] },
+    Ball { radius: f32 },
+    Cylinder { half_height: f32, radius: f32 },
+    Capsule { half_height: f32, radius: f32 },
+    Gltf { path: String },
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum RoomMotion {
+    Elevator { end: [f32; 3], speed: f32, pause: f32 },
+    Spin { angular_velocity: [f32; 3] },
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RoomInteractable {
+    pub position: [f32; 3],
+    pub radius: f32,
+    pub kind: InteractableKind,
+    #[serde(default)]
+    pub material: Option<RoomMaterial>, // interaction spheres are only drawn when given one
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum InteractableKind {
+    Dialog { file: String, #[serde(default)] shot: Option<DialogShot> },
+    Door { room: String, spawn_point: String },
+    Item { item: String, #[serde(default = "one")] count: u32 },
+}
+
+fn one() -> u32 {
+    1
+}
+
+// walk-in volume that moves the player to another room
+#[derive(Debug, Deserialize, Clone)]
+pub struct RoomPortal {
+    pub position: [f32; 3],
+    pub half_extents: [f32; 3],
+    pub room: String,
+    pub spawn_point: String,
+}
+
+// box shaped sensor that runs actions as the player walks through it
+#[derive(Debug, Deserialize, Clone)]
+pub struct RoomTrigger {
+    pub position: [f32; 3],
+    pub half_extents: [f32; 3],
+    pub volume: TriggerVolume,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpawnPoint {
+    pub name: String,
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub yaw: f32, // camera yaw in degrees on arrival
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RoomAmbient {
+    pub color: [f32; 3],
+    pub brightness: f32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub enum RoomLight {
+    Point { position: [f32; 3], color: [f32; 3], intensity: f32, range: f32 },
+    Directional { rotation: [f32; 3], color: [f32; 3], illuminance: f32 },
+}
+
+// request to swap the current room out for another one
+#[derive(Event)]
+pub struct LoadRoom {
+    pub room: String,
+    pub spawn_point: Option<String>, // first spawn point in the file when None
+}
+
+// sent once a requested room has finished spawning
+#[derive(Event)]
+pub struct RoomChanged {
+    pub from: Option<String>,
+    pub to: String,
+    pub spawn_point: Option<String>,
+}
+
+#[derive(Resource, Default)]
+pub struct CurrentRoom {
+    pub name: Option<String>,
+    pending: Option<PendingRoom>,
+    awaiting_spawn: Option<SpawnRequest>,
+}
+
+// spawn points can come from glTF nodes that appear a few frames after the room itself
+struct SpawnRequest {
+    name: Option<String>, // any spawn point will do when None
+}
+
+impl CurrentRoom {
+    pub fn is_loading(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    // the room is in but the player hasn't been moved to its spawn point yet
+    pub fn is_placing_player(&self) -> bool {
+        self.awaiting_spawn.is_some()
+    }
+}
+
+struct PendingRoom {
+    name: String,
+    handle: Handle<RoomAsset>,
+    spawn_point: Option<String>,
+    previous: Option<String>,
+}
+
+// root of everything spawned for a room, despawned recursively on unload
+#[derive(Component)]
+pub struct Room;
+
+// named place the player can arrive at, from the room file or a glTF node
+#[derive(Component)]
+pub struct RoomSpawnPoint {
+    pub name: String,
+    pub yaw: f32, // camera yaw in degrees on arrival
+}
+
+fn room_path(name: &str) -> String {
+    format!("rooms/{}.room.ron", name)
+}
+
+fn euler_degrees(rotation: [f32; 3]) -> Quat {
+    let [x, y, z] = rotation;
+    Quat::from_euler(EulerRot::YXZ, y.to_radians(), x.to_radians(), z.to_radians())
+}
+
+fn handle_load_room(
+    mut event_reader: EventReader<LoadRoom>,
+    mut current: ResMut<CurrentRoom>,
+    asset_server: Res<AssetServer>,
+) {
+    // only the latest request matters if several arrive in one frame
+    let Some(event) = event_reader.read().last() else {
+        return;
+    };
+
+    // the current room stays up until the new one has loaded, a superseded request keeps its origin
+    let previous = current
+        .name
+        .take()
+        .or_else(|| current.pending.take().and_then(|pending| pending.previous));
+    current.awaiting_spawn = None;
+    current.pending = Some(PendingRoom {
+        name: event.room.clone(),
+        handle: asset_server.load(room_path(&event.room)),
+        spawn_point: event.spawn_point.clone(),
+        previous,
+    });
+}
+
+fn spawn_pending_room(
+    mut commands: Commands,
+    mut current: ResMut<CurrentRoom>,
+    mut changed_writer: EventWriter<RoomChanged>,
+    mut ambient: ResMut<AmbientLight>,
+    inventory: Res<Inventory>,
+    room_query: Query<Entity, With<Room>>,
+    rooms: Res<Assets<RoomAsset>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(pending) = &current.pending else {
+        return;
+    };
+
+    let Some(room) = rooms.get(&pending.handle) else {
+        if let Some(LoadState::Failed(err)) = asset_server.get_load_state(&pending.handle) {
+            warn!("Failed to load room {}: {}", pending.name, err);
+            // keep the player where they were
+            current.name = pending.previous.clone();
+            current.pending = None;
+        }
+        return;
+    };
+
+    for old_room in room_query.iter() {
+        commands.entity(old_room).despawn_recursive();
+    }
+
+    commands
+        .spawn((Room, Name::new(pending.name.clone()), Transform::default(), Visibility::default()))
+        .with_children(|parent| {
+            for geometry in &room.geometry {
+                spawn_geometry(parent, geometry, &asset_server);
+            }
+            for (index, interactable) in room.interactables.iter().enumerate() {
+                let key = pickup_key(&pending.name, index);
+                // items stay gone once picked up
+                if matches!(interactable.kind, InteractableKind::Item { .. }) && inventory.collected.contains(&key) {
+                    continue;
+                }
+                spawn_interactable(parent, interactable, key, &asset_server);
+            }
+            for portal in &room.portals {
+                spawn_portal(parent, portal);
+            }
+            for light in &room.lights {
+                spawn_light(parent, light);
+            }
+            for npc in &room.npcs {
+                parent.spawn((NpcPlacement::new(npc.clone()), Transform::default(), Visibility::default()));
+            }
+            for trigger in &room.triggers {
+                let [x, y, z] = trigger.half_extents;
+                let mut entity = parent.spawn(Transform::from_translation(Vec3::from_array(trigger.position)));
+                insert_trigger(&mut entity, trigger.volume.clone(), Collider::cuboid(x, y, z), &asset_server);
+            }
+            for spawn_point in &room.spawn_points {
+                parent.spawn((
+                    RoomSpawnPoint {
+                        name: spawn_point.name.clone(),
+                        yaw: spawn_point.yaw,
+                    },
+                    Transform::from_translation(Vec3::from_array(spawn_point.position)),
+                ));
+            }
+        });
+
+    *ambient = match &room.ambient {
+        Some(RoomAmbient { color: [r, g, b], brightness }) => AmbientLight {
+            color: Color::srgb(*r, *g, *b),
+            brightness: *brightness,
+        },
+        None => AmbientLight::default(),
+    };
+
+    // without a requested name prefer the first spawn point in the file
+    let spawn_name = pending
+        .spawn_point
+        .clone()
+        .or_else(|| room.spawn_points.first().map(|point| point.name.clone()));
+
+    info!("Loaded room: {}", pending.name);
+    changed_writer.send(RoomChanged {
+        from: pending.previous.clone(),
+        to: pending.name.clone(),
+        spawn_point: pending.spawn_point.clone(),
+    });
+    current.name = Some(pending.name.clone());
+    current.pending = None;
+    current.awaiting_spawn = Some(SpawnRequest { name: spawn_name });
+}
+
+fn place_player(
+    mut current: ResMut<CurrentRoom>,
+    spawn_query: Query<(&RoomSpawnPoint, Ref<GlobalTransform>)>,
+    scene_query: Query<(&SceneRoot, &Parent, Option<Ref<SceneInstance>>)>,
+    room_query: Query<(), With<Room>>,
+    mut player_query: Query<(&mut Transform, &mut PlayerPhysics), With<Player>>,
+    mut orbit_query: Query<&mut CameraOrbit>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(request) = &current.awaiting_spawn else {
+        return;
+    };
+
+    let Some((spawn, global)) = spawn_query
+        .iter()
+        .find(|(spawn, _)| request.name.as_ref().is_none_or(|name| &spawn.name == name))
+    else {
+        // glTF spawn points turn up the frame after their scene, so only give up once every scene in the room is in
+        let scenes_pending = scene_query.iter().any(|(scene, parent, instance)| {
+            room_query.contains(parent.get())
+                && match instance {
+                    Some(instance) => instance.is_added(),
+                    None => !matches!(asset_server.get_load_state(&scene.0), Some(LoadState::Failed(_))),
+                }
+        });
+        if scenes_pending {
+            return;
+        }
+
+        let room = current.name.clone().unwrap_or_default();
+        if let Some(name) = &request.name {
+            // a typo in the room or glTF, or a save from before a spawn point was renamed
+            warn!("No spawn point {} in room {}, using the first one", name, room);
+            current.awaiting_spawn = Some(SpawnRequest { name: None });
+        } else {
+            warn!("Room {} has no spawn points, placing the player at its origin", room);
+            if let Ok((mut transform, mut physics)) = player_query.get_single_mut() {
+                transform.translation = Vec3::ZERO;
+                physics.velocity = Vec3::ZERO;
+            }
+            current.awaiting_spawn = None;
+        }
+        return;
+    };
+    // freshly spawned points haven't had their global transform propagated yet
+    if global.is_added() {
+        return;
+    }
+
+    if let Ok((mut transform, mut physics)) = player_query.get_single_mut() {
+        transform.translation = global.translation();
+        physics.velocity = Vec3::ZERO;
+    }
+    if let Ok(mut orbit) = orbit_query.get_single_mut() {
+        orbit.yaw = spawn.yaw.to_radians();
+        orbit.target_yaw = orbit.yaw;
+    }
+    current.awaiting_spawn = None;
+}
+
+fn spawn_geometry(parent: &mut ChildBuilder, geometry: &RoomGeometry, asset_server: &AssetServer) {
+    let transform = Transform::from_translation(Vec3::from_array(geometry.position))
+        .with_rotation(euler_degrees(geometry.rotation));
+
+    let collider = match &geometry.shape {
+        RoomShape::Cuboid { half_extents: [x, y, z] } => Collider::cuboid(*x, *y, *z),
+        RoomShape::Ball { radius } => Collider::ball(*radius),
+        RoomShape::Cylinder { half_height, radius } => Collider::cylinder(*half_height, *radius),
+        RoomShape::Capsule { half_height, radius } => Collider::capsule_y(*half_height, *radius),
+        RoomShape::Gltf { path } => {
+            parent.spawn((
+                SceneRoot(asset_server.load(GltfAssetLabel::Scene(0).from_asset(path.clone()))),
+                transform,
+            ));
+            return;
+        }
+    };
+
+    let mut entity = parent.spawn((collider, transform));
+
+    if geometry.climbable {
+        entity.insert((Sensor, Climbable));
+    }
+    if geometry.camera_transparent {
+        entity.insert(CameraTransparent);
+    }
+    if !geometry.hidden {
+        let material = match &geometry.material {
+            Some(material) => material.to_material(),
+            None if geometry.climbable => StandardMaterial::from_color(Color::srgb(0.45, 0.3, 0.15)),
+            None => StandardMaterial::from_color(Color::srgb(0.6, 0.6, 0.6)),
+        };
+        entity.insert(ColliderVisual(material));
+    }
+
+    match &geometry.motion {
+        Some(RoomMotion::Elevator { end, speed, pause }) => {
+            entity.insert((
+                RigidBody::KinematicPositionBased,
+                Elevator::new(transform.translation, Vec3::from_array(*end), *speed, *pause),
+                PlatformVelocity::default(),
+            ));
+        }
+        Some(RoomMotion::Spin { angular_velocity }) => {
+            entity.insert((
+                RigidBody::KinematicPositionBased,
+                Spinner { angular_velocity: Vec3::from_array(*angular_velocity) },
+                PlatformVelocity::default(),
+            ));
+        }
+        None if !geometry.climbable => {
+            entity.insert(RigidBody::Fixed);
+        }
+        None => {}
+    }
+}
+
+fn spawn_interactable(parent: &mut ChildBuilder, interactable: &RoomInteractable, key: String, asset_server: &AssetServer) {
+    let mut entity = parent.spawn((
+        Collider::ball(interactable.radius),
+        Transform::from_translation(Vec3::from_array(interactable.position)),
+        Interactable,
+        CameraTransparent,
+    ));
+    if let Some(material) = &interactable.material {
+        entity.insert(ColliderVisual(material.to_material()));
+    }
+
+    match &interactable.kind {
+        InteractableKind::Dialog { file, shot } => {
+            entity.insert((
+                DialogTrigger,
+                DialogEntity,
+                DialogData {
+                    dialog_file: asset_server.load(file.clone()),
+                },
+            ));
+            if let Some(shot) = shot {
+                entity.insert(DialogCamera(*shot));
+            }
+        }
+        InteractableKind::Door { room, spawn_point } => {
+            entity.insert((
+                DoorTrigger,
+                Door {
+                    room: room.clone(),
+                    spawn_point: spawn_point.clone(),
+                },
+            ));
+        }
+        InteractableKind::Item { item, count } => {
+            entity.insert((
+                ItemTrigger,
+                ItemPickup {
+                    item: item.clone(),
+                    count: *count,
+                    key,
+                },
+            ));
+        }
+    }
+}
+
+fn spawn_portal(parent: &mut ChildBuilder, portal: &RoomPortal) {
+    let [x, y, z] = portal.half_extents;
+    parent.spawn((
+        Collider::cuboid(x, y, z),
+        Sensor,
+        Transform::from_translation(Vec3::from_array(portal.position)),
+        Door {
+            room: portal.room.clone(),
+            spawn_point: portal.spawn_point.clone(),
+        },
+        Portal,
+    ));
+}
+
+fn spawn_light(parent: &mut ChildBuilder, light: &RoomLight) {
+    match light {
+        RoomLight::Point { position, color, intensity, range } => {
+            let [r, g, b] = *color;
+            parent.spawn((
+                PointLight {
+                    color: Color::srgb(r, g, b),
+                    intensity: *intensity,
+                    range: *range,
+                    shadows_enabled: true,
+                    ..default()
+                },
+                Transform::from_translation(Vec3::from_array(*position)),
+            ));
+        }
+        RoomLight::Directional { rotation, color, illuminance } => {
+            let [r, g, b] = *color;
+            parent.spawn((
+                DirectionalLight {
+                    color: Color::srgb(r, g, b),
+                    illuminance: *illuminance,
+                    shadows_enabled: true,
+                    ..default()
+                },
+                Transform::from_rotation(euler_degrees(*rotation)),
+            ));
+        }
+    }
+}
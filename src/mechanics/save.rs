@@ -0,0 +1,103 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::mechanics::flags::StoryFlags;
+use crate::mechanics::inventory::Inventory;
+use crate::mechanics::quest::QuestLog;
+use crate::mechanics::room::{LoadRoom, RoomChanged};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveData>()
+            .add_event::<SaveEvent>()
+            .add_systems(Update, (track_room_changes, handle_save_events).chain());
+    }
+}
+
+const SAVE_PATH: &str = "save.ron";
+
+// everything needed to put the player back where they were, kept up to date as the game runs
+#[derive(Resource, Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct SaveData {
+    pub room: Option<String>,
+    pub spawn_point: Option<String>,
+    pub previous_room: Option<String>, // room the player was in before the last transition
+    pub flags: BTreeSet<String>,
+    pub inventory: Inventory,
+    pub quests: QuestLog,
+}
+
+#[derive(Event)]
+pub enum SaveEvent {
+    Save,
+    Load,
+}
+
+impl SaveData {
+    fn read() -> Result<Self, String> {
+        let contents = std::fs::read_to_string(SAVE_PATH).map_err(|err| err.to_string())?;
+        ron::from_str(&contents).map_err(|err| err.to_string())
+    }
+
+    fn write(&self) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        std::fs::write(SAVE_PATH, contents).map_err(|err| err.to_string())
+    }
+}
+
+fn track_room_changes(
+    mut event_reader: EventReader<RoomChanged>,
+    mut save_data: ResMut<SaveData>,
+) {
+    for event in event_reader.read() {
+        save_data.previous_room = event.from.clone();
+        save_data.room = Some(event.to.clone());
+        save_data.spawn_point = event.spawn_point.clone();
+    }
+}
+
+fn handle_save_events(
+    mut event_reader: EventReader<SaveEvent>,
+    mut save_data: ResMut<SaveData>,
+    mut flags: ResMut<StoryFlags>,
+    mut inventory: ResMut<Inventory>,
+    mut quests: ResMut<QuestLog>,
+    mut load_writer: EventWriter<LoadRoom>,
+) {
+    for event in event_reader.read() {
+        match event {
+            SaveEvent::Save => {
+                save_data.flags = flags.0.clone();
+                save_data.inventory = inventory.clone();
+                save_data.quests = quests.clone();
+                match save_data.write() {
+                    Ok(()) => info!("Game saved"),
+                    Err(err) => warn!("Failed to save game: {}", err),
+                }
+            }
+            SaveEvent::Load => match SaveData::read() {
+                Ok(loaded) => {
+                    if let Some(room) = &loaded.room {
+                        load_writer.send(LoadRoom {
+                            room: room.clone(),
+                            spawn_point: loaded.spawn_point.clone(),
+                        });
+                    }
+                    flags.0 = loaded.flags.clone();
+                    *inventory = loaded.inventory.clone();
+                    *quests = loaded.quests.clone();
+                    *save_data = loaded;
+                    info!("Game loaded");
+                }
+                Err(err) => warn!("Failed to load game: {}", err),
+            },
+        }
+    }
+}
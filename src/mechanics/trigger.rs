@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::gui_plugin::{GameState, GameStateMarker};
+use crate::mechanics::dialog::{DialogData, DialogEntity};
+use crate::mechanics::door::Door;
+use crate::mechanics::flags::{set_flag, FlagChanged, StoryFlags};
+use crate::player_plugin::{InteractType, Player};
+
+pub struct TriggerPlugin;
+
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TriggerEvent>()
+            .add_systems(Update, (detect_triggers, run_trigger_actions).chain());
+    }
+}
+
+// sensor volume that reports the player (or anything, see filter) entering, staying and leaving
+#[derive(Component, Deserialize, Clone, Debug)]
+pub struct TriggerVolume {
+    #[serde(default)]
+    pub filter: TriggerFilter,
+    #[serde(default)]
+    pub once: bool, // only ever fire enter actions a single time
+    #[serde(default)]
+    pub cooldown: f32, // seconds after firing before enter actions can fire again
+    #[serde(default)]
+    pub on_enter: Vec<TriggerAction>,
+    #[serde(default)]
+    pub on_exit: Vec<TriggerAction>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct TriggerFilter {
+    pub any_collider: bool, // count any collider instead of only the player
+    pub requires_flag: Option<String>,
+    pub blocked_by_flag: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum TriggerAction {
+    StartDialog(String),
+    ChangeRoom { room: String, spawn_point: String },
+    SetFlag(String),
+    ClearFlag(String),
+    PlaySound(String),
+}
+
+// runtime bookkeeping, inserted alongside TriggerVolume
+#[derive(Component, Default)]
+pub struct TriggerState {
+    pub occupants: Vec<Entity>,
+    pub entering: Vec<Entity>, // came in during a pause or dialog, entered once gameplay resumes
+    pub leaving: Vec<Entity>,  // left during a pause or dialog, exited once gameplay resumes
+    pub fired: bool,
+    pub cooldown_left: f32,
+}
+
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct TriggerEvent {
+    pub trigger: Entity,
+    pub other: Entity, // what entered the volume, the player unless the filter allows anything
+    pub phase: TriggerPhase,
+}
+
+// stay is sent every frame of gameplay something is inside
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriggerPhase {
+    Enter,
+    Stay,
+    Exit,
+}
+
+// turns a spawned entity into a trigger volume. Dialog and room change actions are attached up front
+// so the dialog file is loaded by the time the player walks in, one of each per volume
+pub fn insert_trigger(entity: &mut EntityCommands, volume: TriggerVolume, collider: Collider, asset_server: &AssetServer) {
+    for action in volume.on_enter.iter().chain(volume.on_exit.iter()) {
+        match action {
+            TriggerAction::StartDialog(path) => {
+                entity.insert((
+                    DialogEntity,
+                    DialogData {
+                        dialog_file: asset_server.load(path.clone()),
+                    },
+                ));
+            }
+            TriggerAction::ChangeRoom { room, spawn_point } => {
+                entity.insert(Door {
+                    room: room.clone(),
+                    spawn_point: spawn_point.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // the player is a kinematic body, which rapier ignores against fixed sensors by default
+    entity.insert((
+        collider,
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC,
+        volume,
+        TriggerState::default(),
+    ));
+}
+
+fn detect_triggers(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut trigger_events: EventWriter<TriggerEvent>,
+    mut trigger_query: Query<(Entity, &TriggerVolume, &mut TriggerState)>,
+    player_query: Query<(), With<Player>>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    flags: Res<StoryFlags>,
+    time: Res<Time>,
+) {
+    // nothing enters or leaves while a menu or dialog is up, it's held until gameplay resumes
+    let in_gameplay = game_query.get_single().map(|state| state.in_gameplay()).unwrap_or(true);
+
+    for event in collision_events.read() {
+        let (a, b, started) = match event {
+            CollisionEvent::Started(a, b, _) => (*a, *b, true),
+            CollisionEvent::Stopped(a, b, _) => (*a, *b, false),
+        };
+
+        // either side of the pair can be the trigger
+        for (trigger, other) in [(a, b), (b, a)] {
+            let Ok((_, volume, mut state)) = trigger_query.get_mut(trigger) else {
+                continue;
+            };
+            if !volume.filter.any_collider && !player_query.contains(other) {
+                continue;
+            }
+
+            if started {
+                let allowed = volume.filter.requires_flag.as_ref().is_none_or(|flag| flags.is_set(flag))
+                    && volume.filter.blocked_by_flag.as_ref().is_none_or(|flag| !flags.is_set(flag));
+                if !allowed {
+                    continue;
+                }
+                // back in before its exit went out, as if it never left
+                if let Some(index) = state.leaving.iter().position(|leaving| *leaving == other) {
+                    state.leaving.remove(index);
+                    state.occupants.push(other);
+                } else if !state.occupants.contains(&other) && !state.entering.contains(&other) {
+                    if in_gameplay {
+                        state.occupants.push(other);
+                        trigger_events.send(TriggerEvent { trigger, other, phase: TriggerPhase::Enter });
+                    } else {
+                        state.entering.push(other);
+                    }
+                }
+            } else if let Some(index) = state.entering.iter().position(|entering| *entering == other) {
+                state.entering.remove(index);
+            } else if let Some(index) = state.occupants.iter().position(|occupant| *occupant == other) {
+                state.occupants.remove(index);
+                if in_gameplay {
+                    trigger_events.send(TriggerEvent { trigger, other, phase: TriggerPhase::Exit });
+                } else {
+                    state.leaving.push(other);
+                }
+            }
+        }
+    }
+
+    for (trigger, _, mut state) in trigger_query.iter_mut() {
+        state.cooldown_left = (state.cooldown_left - time.delta_secs()).max(0.0);
+        if !in_gameplay {
+            continue;
+        }
+        for other in std::mem::take(&mut state.leaving) {
+            trigger_events.send(TriggerEvent { trigger, other, phase: TriggerPhase::Exit });
+        }
+        for other in std::mem::take(&mut state.entering) {
+            state.occupants.push(other);
+            trigger_events.send(TriggerEvent { trigger, other, phase: TriggerPhase::Enter });
+        }
+        for other in state.occupants.iter() {
+            trigger_events.send(TriggerEvent { trigger, other: *other, phase: TriggerPhase::Stay });
+        }
+    }
+}
+
+fn run_trigger_actions(
+    mut commands: Commands,
+    mut trigger_events: EventReader<TriggerEvent>,
+    mut trigger_query: Query<(&TriggerVolume, &mut TriggerState)>,
+    mut interact_writer: EventWriter<InteractType>,
+    mut flag_writer: EventWriter<FlagChanged>,
+    mut flags: ResMut<StoryFlags>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in trigger_events.read() {
+        let trigger = event.trigger;
+        let actions = match event.phase {
+            TriggerPhase::Enter => {
+                let Ok((volume, mut state)) = trigger_query.get_mut(trigger) else {
+                    continue;
+                };
+                if (volume.once && state.fired) || state.cooldown_left > 0.0 {
+                    continue;
+                }
+                state.fired = true;
+                state.cooldown_left = volume.cooldown;
+                volume.on_enter.clone()
+            }
+            TriggerPhase::Exit => {
+                let Ok((volume, _)) = trigger_query.get(trigger) else {
+                    continue;
+                };
+                volume.on_exit.clone()
+            }
+            TriggerPhase::Stay => continue,
+        };
+
+        for action in actions {
+            match action {
+                // same path as talking to something or opening a door, the volume is the speaker
+                TriggerAction::StartDialog(_) => {
+                    interact_writer.send(InteractType::Dialog(trigger));
+                }
+                TriggerAction::ChangeRoom { .. } => {
+                    interact_writer.send(InteractType::Door(trigger));
+                }
+                TriggerAction::SetFlag(flag) => set_flag(&mut flags, &mut flag_writer, &flag, true),
+                TriggerAction::ClearFlag(flag) => set_flag(&mut flags, &mut flag_writer, &flag, false),
+                TriggerAction::PlaySound(path) => {
+                    commands.spawn((AudioPlayer::<AudioSource>(asset_server.load(path)), PlaybackSettings::DESPAWN));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+// gives primitive colliders a mesh of the same shape, so levels can be blocked out without models
+pub struct VisualsPlugin;
+
+impl Plugin for VisualsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, add_collider_meshes);
+    }
+}
+
+// put on an entity with a Collider to render its shape with this material
+#[derive(Component, Clone)]
+pub struct ColliderVisual(pub StandardMaterial);
+
+// material as written in room files
+#[derive(Debug, Deserialize, Clone)]
+pub struct RoomMaterial {
+    pub color: [f32; 3],
+    #[serde(default = "default_alpha")]
+    pub alpha: f32,
+    #[serde(default = "default_roughness")]
+    pub roughness: f32,
+    #[serde(default)]
+    pub metallic: f32,
+}
+
+fn default_alpha() -> f32 {
+    1.0
+}
+
+fn default_roughness() -> f32 {
+    0.9
+}
+
+impl RoomMaterial {
+    pub fn to_material(&self) -> StandardMaterial {
+        let [r, g, b] = self.color;
+        StandardMaterial {
+            base_color: Color::srgba(r, g, b, self.alpha),
+            perceptual_roughness: self.roughness,
+            metallic: self.metallic,
+            alpha_mode: if self.alpha < 1.0 { AlphaMode::Blend } else { AlphaMode::Opaque },
+            ..default()
+        }
+    }
+}
+
+fn collider_mesh(collider: &Collider) -> Option<Mesh> {
+    if let Some(cuboid) = collider.as_cuboid() {
+        let size = cuboid.half_extents() * 2.0;
+        return Some(Cuboid::new(size.x, size.y, size.z).into());
+    }
+    if let Some(ball) = collider.as_ball() {
+        return Some(Sphere::new(ball.radius()).into());
+    }
+    if let Some(cylinder) = collider.as_cylinder() {
+        return Some(Cylinder::new(cylinder.radius(), cylinder.half_height() * 2.0).into());
+    }
+    if let Some(capsule) = collider.as_capsule() {
+        // capsules can be built between any two points, the mesh one runs along Y around the origin
+        let (a, b) = (capsule.segment().a(), capsule.segment().b());
+        let mesh: Mesh = Capsule3d::new(capsule.radius(), capsule.height()).into();
+        return Some(
+            mesh.rotated_by(Quat::from_rotation_arc(Vec3::Y, (b - a).normalize_or(Vec3::Y)))
+                .translated_by((a + b) * 0.5),
+        );
+    }
+    None
+}
+
+fn add_collider_meshes(
+    mut commands: Commands,
+    visual_query: Query<(Entity, &Collider, &ColliderVisual), Added<ColliderVisual>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, collider, visual) in visual_query.iter() {
+        let Some(mesh) = collider_mesh(collider) else {
+            warn!("No mesh for the collider shape on {}", entity);
+            continue;
+        };
+        commands.entity(entity).insert((
+            Mesh3d(meshes.add(mesh)),
+            MeshMaterial3d(materials.add(visual.0.clone())),
+        ));
+    }
+}
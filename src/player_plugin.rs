@@ -1,32 +1,59 @@
-use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
 
+use crate::camera_plugin::CameraOrbit;
 use crate::gui_plugin::{GameState, GameStateMarker};
+use crate::mechanics::platform::PlatformRider;
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<InteractType>()
+            .init_resource::<InteractionFocus>()
             .add_systems(Startup, spawn_player)
             .add_systems(Update, (
-                player_controls, 
-                camera_control, 
-                camera_follow,
+                (player_crouch, player_controls).chain().in_set(PlayerMovement),
+                update_player_model.after(player_crouch),
                 handle_interaction,
             ));
+
+        // fly mode is a debugging aid, only built with the debug feature
+        #[cfg(feature = "debug")]
+        app.add_event::<ToggleNoclip>()
+            .add_systems(Update, (
+                toggle_noclip.before(PlayerMovement),
+                noclip_controls.in_set(PlayerMovement),
+            ));
     }
 }
 
-fn spawn_player(mut commands: Commands) {
+// player movement systems, for anything that needs to run around the controller update
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerMovement;
+
+pub const PLAYER_RADIUS: f32 = 0.4;
+pub const STANDING_HEIGHT: f32 = 1.8;
+pub const CROUCHING_HEIGHT: f32 = 0.9;
+pub const INT_RADIUS: f32 = 4.5; // reach of the interact key, measured from the player's feet
+
+pub fn player_collider(height: f32) -> Collider {
+    Collider::capsule(
+        Vec3::new(0.0, 0.0, 0.0).into(), // Start at feet
+        Vec3::new(0.0, height, 0.0).into(), // End at head
+        PLAYER_RADIUS,
+    )
+}
+
+fn spawn_player(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
     commands.spawn((
         RigidBody::KinematicPositionBased,
-        Collider::capsule(
-            Vec3::new(0.0, 0.0, 0.0).into(), // Start at feet
-            Vec3::new(0.0, 1.8, 0.0).into(), // End at head (1.8 units tall)
-            0.4,
-        ),
+        player_collider(STANDING_HEIGHT),
         KinematicCharacterController {
             max_slope_climb_angle: 0.7,
             autostep: Some(CharacterAutostep {
@@ -40,40 +67,36 @@ fn spawn_player(mut commands: Commands) {
         },
         KinematicCharacterControllerOutput::default(),
         PlayerPhysics::default(),
+        MovementMode::default(),
+        PlatformRider::default(),
         Transform::from_xyz(0.0, 1.0, 0.0),
         Player,
-    ));
-    commands
-        .spawn((CameraOrbit::default(), Transform::default()))
-        .with_children(|parent| {
-            parent
-                .spawn(Camera3d { ..default() })
-                .insert(Transform::from_xyz(0.0, 2.0, 10.0));
-        });
-}
-
-#[derive(Component)]
-struct CameraOrbit {
-    pitch: f32,
-    yaw: f32,
-}
-
-impl Default for CameraOrbit {
-    fn default() -> Self {
-        Self {
-            pitch: 0.0,
-            yaw: 0.0,
-        }
-    }
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            Mesh3d(meshes.add(Capsule3d::new(PLAYER_RADIUS, STANDING_HEIGHT))),
+            MeshMaterial3d(materials.add(Color::srgb(0.8, 0.7, 0.6))),
+            Transform::from_xyz(0.0, STANDING_HEIGHT / 2.0, 0.0),
+            PlayerModel,
+        ));
+    });
 }
 
 // markers
 #[derive(Component)]
 pub struct Player;
 
+// visible body of the player, hidden in first person
+#[derive(Component)]
+pub struct PlayerModel;
+
 #[derive(Component)]
 pub struct Interactable;
 
+// sensor volume the player can climb (ladders, vines), expects a cuboid collider
+#[derive(Component)]
+pub struct Climbable;
+
 // triggers
 #[derive(Component)]
 pub struct DialogTrigger;
@@ -84,17 +107,41 @@ pub struct DoorTrigger;
 #[derive(Component)]
 pub struct ItemTrigger;
 
-#[derive(Event)]
+// interactables within INT_RADIUS this frame, which E acts on, and the nearest one for the debug gizmos
+#[derive(Resource, Default)]
+pub struct InteractionFocus {
+    pub in_range: Vec<Entity>,
+    pub target: Option<Entity>,
+}
+
+#[derive(Event, Clone)]
 pub enum InteractType {
     Dialog(Entity),
     Door(Entity),
     Item(Entity),
 }
 
+// player flies through everything while this is on, see noclip_controls
+#[cfg(feature = "debug")]
 #[derive(Component)]
+pub struct Noclip;
+
+#[cfg(feature = "debug")]
+#[derive(Event)]
+pub struct ToggleNoclip;
+
+#[derive(Component, Default, Clone, Copy, PartialEq, Debug)]
+pub enum MovementMode {
+    #[default]
+    Walking,
+    Climbing(Entity),
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
 pub struct PlayerPhysics {
     pub velocity: Vec3,
     pub grounded_timer: f32,
+    pub crouching: bool,
 }
 
 impl Default for PlayerPhysics {
@@ -102,68 +149,99 @@ impl Default for PlayerPhysics {
         Self {
             velocity: Vec3::ZERO,
             grounded_timer: 0.0,
+            crouching: false,
         }
     }
 }
 
-fn camera_control(
-    mut mouse_motion: EventReader<MouseMotion>,
-    mut query: Query<(&mut Transform, &mut CameraOrbit)>,
+fn player_crouch(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Transform, &mut PlayerPhysics, &MovementMode), With<Player>>,
+    rapier_context: Query<&RapierContext>,
+    input: Res<ButtonInput<KeyCode>>,
     game_query: Query<&GameState, With<GameStateMarker>>,
-    time: Res<Time>,
+    #[cfg(feature = "debug")] noclip_query: Query<(), (With<Player>, With<Noclip>)>,
 ) {
     if let Ok(game_state) = game_query.get_single() {
-        if game_state.paused { return; }
+        if !game_state.in_gameplay() { return; }
     }
+    #[cfg(feature = "debug")]
+    if !noclip_query.is_empty() { return; }
 
-    const ROTATION_SPEED: f32 = 0.3;
-    const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.1;
-
-    if let Ok((mut transform, mut orbit)) = query.get_single_mut() {
-        let mut rotation = Vec2::ZERO;
-        for event in mouse_motion.read() {
-            rotation += event.delta * ROTATION_SPEED * time.delta_secs();
-        }
-
-        // (left/right)
-        orbit.yaw -= rotation.x;
+    let Ok((entity, transform, mut physics, mode)) = query.get_single_mut() else {
+        return;
+    };
+    let wants_crouch = input.pressed(KeyCode::ControlLeft) && *mode == MovementMode::Walking;
+
+    if wants_crouch && !physics.crouching {
+        physics.crouching = true;
+        commands.entity(entity).insert(player_collider(CROUCHING_HEIGHT));
+    } else if !wants_crouch && physics.crouching {
+        let Ok(context) = rapier_context.get_single() else {
+            return;
+        };
 
-        // (up/down)
-        orbit.pitch = (orbit.pitch - rotation.y).clamp(-MAX_PITCH, MAX_PITCH);
+        // sweep the head sphere up through the height we'd regain, stay down if anything is in the way
+        let head = transform.translation + Vec3::Y * CROUCHING_HEIGHT;
+        let blocked = context.cast_shape(
+            head,
+            Quat::IDENTITY,
+            Vec3::Y,
+            &Collider::ball(PLAYER_RADIUS * 0.95),
+            ShapeCastOptions::with_max_time_of_impact(STANDING_HEIGHT - CROUCHING_HEIGHT),
+            QueryFilter::default().exclude_sensors().exclude_collider(entity),
+        );
 
-        transform.rotation =
-            Quat::from_axis_angle(Vec3::Y, orbit.yaw) * Quat::from_axis_angle(Vec3::X, orbit.pitch);
+        if blocked.is_none() {
+            physics.crouching = false;
+            commands.entity(entity).insert(player_collider(STANDING_HEIGHT));
+        }
     }
 }
 
-fn camera_follow(
-    player_query: Query<&Transform, (With<PlayerPhysics>, With<Player>, Without<CameraOrbit>)>,
-    mut camera_query: Query<&mut Transform, With<CameraOrbit>>,
+// squash the model to match the capsule while crouching
+fn update_player_model(
+    player_query: Query<&PlayerPhysics, With<Player>>,
+    mut model_query: Query<&mut Transform, With<PlayerModel>>,
 ) {
-    if let (Ok(player_transform), Ok(mut camera_transform)) =
-        (player_query.get_single(), camera_query.get_single_mut())
-    {
-        camera_transform.translation = player_transform.translation;
-    }
+    let (Ok(physics), Ok(mut transform)) = (player_query.get_single(), model_query.get_single_mut()) else {
+        return;
+    };
+
+    let height = if physics.crouching { CROUCHING_HEIGHT } else { STANDING_HEIGHT };
+    transform.translation.y = height / 2.0;
+    transform.scale.y = (height + 2.0 * PLAYER_RADIUS) / (STANDING_HEIGHT + 2.0 * PLAYER_RADIUS);
 }
 
 fn player_controls(
     mut query: Query<(
+        Entity,
+        &Transform,
+        &Collider,
         &mut KinematicCharacterController,
         &mut PlayerPhysics,
+        &mut MovementMode,
         &KinematicCharacterControllerOutput,
     ), With<Player>>,
-    camera_orbit_query: Query<&Transform, With<CameraOrbit>>,
+    camera_orbit_query: Query<&Transform, (With<CameraOrbit>, Without<Player>)>,
+    climbable_query: Query<(&Transform, &Collider), (With<Climbable>, Without<Player>)>,
+    rapier_context: Query<&RapierContext>,
     input: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
     game_query: Query<&GameState, With<GameStateMarker>>,
+    #[cfg(feature = "debug")] noclip_query: Query<(), (With<Player>, With<Noclip>)>,
 ) {
     if let Ok(game_state) = game_query.get_single() {
-        if game_state.paused || game_state.in_dialog { return; }
+        if !game_state.in_gameplay() { return; }
     }
+    // no gravity or collision while flying, noclip_controls moves the player instead
+    #[cfg(feature = "debug")]
+    if !noclip_query.is_empty() { return; }
 
     const WALK: f32 = 5.0;
     const RUN: f32 = 8.0;
+    const CROUCH: f32 = 2.5;
+    const CLIMB: f32 = 3.0;
     const FRICTION: f32 = 0.875;
     const JUMP_FORCE: f32 = 8.0;
     const GRAVITY: f32 = -9.81;
@@ -172,11 +250,14 @@ fn player_controls(
     const VELOCITY_THRESHOLD: f32 = 0.01;
     const MOVEMENT_THRESHOLD: f32 = 0.0001;
     const GROUNDED_THRESHOLD: f32 = 0.2;
+    const MOUNT_THRESHOLD: f32 = 0.5;
+    const LEDGE_HOP: f32 = 3.0;
+    const JUMP_OFF_PUSH: f32 = 4.0;
 
     let Ok(orbit_transform) = camera_orbit_query.get_single() else {
         return;
     };
-    if let Ok((mut controller, mut physics, output)) = query.get_single_mut() {
+    if let Ok((entity, transform, collider, mut controller, mut physics, mut mode, output)) = query.get_single_mut() {
         if output.grounded {
             physics.grounded_timer = (physics.grounded_timer + time.delta_secs()).min(GROUNDED_THRESHOLD);
         } else {
@@ -208,44 +289,106 @@ fn player_controls(
             direction -= right;
         }
 
-        // getting target speed
-        let speed = if input.pressed(KeyCode::ShiftLeft) {
-            RUN
-        } else {
-            WALK
-        };
+        // mode transitions
+        let current_mode = *mode;
+        match current_mode {
+            MovementMode::Walking => {
+                if physics.crouching || direction == Vec3::ZERO {
+                    // only mount when actively pushing into a ladder
+                } else if let Ok(context) = rapier_context.get_single() {
+                    if let Some(ladder) = touching_climbable(context, entity, transform, collider, &climbable_query) {
+                        if let Ok((ladder_transform, _)) = climbable_query.get(ladder) {
+                            let to_ladder = horizontal_direction(transform.translation, ladder_transform.translation);
+                            if direction.normalize().dot(to_ladder) > MOUNT_THRESHOLD {
+                                *mode = MovementMode::Climbing(ladder);
+                                physics.velocity = Vec3::ZERO;
+                            }
+                        }
+                    }
+                }
+            }
+            MovementMode::Climbing(ladder) => {
+                if let Ok((ladder_transform, ladder_collider)) = climbable_query.get(ladder) {
+                    let to_ladder = horizontal_direction(transform.translation, ladder_transform.translation);
+                    let top = ladder_transform.translation.y
+                        + ladder_collider.as_cuboid().map_or(0.0, |cuboid| cuboid.half_extents().y);
+
+                    if input.just_pressed(KeyCode::Space) {
+                        // jump off, away from the ladder
+                        *mode = MovementMode::Walking;
+                        physics.velocity = -to_ladder * JUMP_OFF_PUSH + Vec3::Y * JUMP_FORCE * 0.6;
+                    } else if transform.translation.y - PLAYER_RADIUS >= top {
+                        // top dismount, hop forward onto the ledge
+                        *mode = MovementMode::Walking;
+                        physics.velocity = to_ladder * WALK + Vec3::Y * LEDGE_HOP;
+                    } else if output.grounded && input.pressed(KeyCode::KeyS) {
+                        // bottom dismount
+                        *mode = MovementMode::Walking;
+                        physics.velocity = Vec3::ZERO;
+                    }
+                } else {
+                    // ladder went away (room unloaded, despawned), fall back to walking
+                    *mode = MovementMode::Walking;
+                }
+            }
+        }
 
-        let mut desired_velocity = if direction != Vec3::ZERO {
-            direction.normalize() * speed
-        } else {
-            Vec3::ZERO
-        };
+        match *mode {
+            MovementMode::Walking => {
+                // getting target speed
+                let speed = if physics.crouching {
+                    CROUCH
+                } else if input.pressed(KeyCode::ShiftLeft) {
+                    RUN
+                } else {
+                    WALK
+                };
+
+                let mut desired_velocity = if direction != Vec3::ZERO {
+                    direction.normalize() * speed
+                } else {
+                    Vec3::ZERO
+                };
+
+                desired_velocity.y = physics.velocity.y;
+
+                if is_grounded && !physics.crouching && input.pressed(KeyCode::Space) {
+                    physics.velocity.y = JUMP_FORCE;
+                }
 
-        desired_velocity.y = physics.velocity.y;
+                if !is_grounded || physics.velocity.y > 0.0 {
+                    let mut gravity_scale = if physics.velocity.y > 0.0 {
+                        JUMP_MULTIPLIER
+                    } else {
+                        FALL_MULTIPLIER
+                    };
 
-        if is_grounded && input.pressed(KeyCode::Space) {
-            physics.velocity.y = JUMP_FORCE;
-        }
+                    if output.is_sliding_down_slope {
+                        gravity_scale *= 0.475;
+                    }
 
-        if !is_grounded || physics.velocity.y > 0.0 {
-            let mut gravity_scale = if physics.velocity.y > 0.0 {
-                JUMP_MULTIPLIER
-            } else {
-                FALL_MULTIPLIER
-            };
+                    physics.velocity.y += GRAVITY * gravity_scale * time.delta_secs();
+                } else if is_grounded {
+                    physics.velocity.y = 0.0;
+                }
 
-            if output.is_sliding_down_slope {
-                gravity_scale *= 0.475;
+                // Smooth movement towards desired velocity
+                physics.velocity = physics.velocity.lerp(desired_velocity, 1.0 - FRICTION);
             }
+            MovementMode::Climbing(_) => {
+                // no gravity on ladders, W/S climbs up and down
+                let mut climb = 0.0;
+                if input.pressed(KeyCode::KeyW) {
+                    climb += 1.0;
+                }
+                if input.pressed(KeyCode::KeyS) {
+                    climb -= 1.0;
+                }
 
-            physics.velocity.y += GRAVITY * gravity_scale * time.delta_secs();
-        } else if is_grounded {
-            physics.velocity.y = 0.0;
+                physics.velocity = Vec3::Y * climb * CLIMB;
+            }
         }
 
-        // Smooth movement towards desired velocity
-        physics.velocity = physics.velocity.lerp(desired_velocity, 1.0 - FRICTION);
-
         if physics.velocity.length_squared() < VELOCITY_THRESHOLD * VELOCITY_THRESHOLD {
             physics.velocity = Vec3::ZERO;
         }
@@ -266,23 +409,132 @@ fn player_controls(
     }
 }
 
+// N or the console's noclip command, the controller picks up from a standstill on exit
+#[cfg(feature = "debug")]
+fn toggle_noclip(
+    mut commands: Commands,
+    mut toggle_events: EventReader<ToggleNoclip>,
+    mut query: Query<(Entity, &mut PlayerPhysics, &mut MovementMode, Has<Noclip>), With<Player>>,
+    input: Res<ButtonInput<KeyCode>>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+) {
+    let in_gameplay = game_query.get_single().map(|state| state.in_gameplay()).unwrap_or(true);
+    let hotkey = in_gameplay && input.just_pressed(KeyCode::KeyN);
+    let toggles = toggle_events.read().count() + usize::from(hotkey);
+    if toggles % 2 == 0 {
+        return;
+    }
+
+    let Ok((entity, mut physics, mut mode, noclip)) = query.get_single_mut() else {
+        return;
+    };
+    if noclip {
+        commands.entity(entity).remove::<(Noclip, ColliderDisabled)>();
+    } else {
+        commands.entity(entity).insert((Noclip, ColliderDisabled));
+    }
+    physics.velocity = Vec3::ZERO;
+    physics.grounded_timer = 0.0;
+    *mode = MovementMode::Walking;
+    info!("Noclip: {}", !noclip);
+}
+
+#[cfg(feature = "debug")]
+fn noclip_controls(
+    mut query: Query<(&mut Transform, &mut KinematicCharacterController), (With<Player>, With<Noclip>)>,
+    camera_orbit_query: Query<&Transform, (With<CameraOrbit>, Without<Player>)>,
+    input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+) {
+    if let Ok(game_state) = game_query.get_single() {
+        if !game_state.in_gameplay() { return; }
+    }
+
+    const FLY: f32 = 10.0;
+    const FAST_MULTIPLIER: f32 = 4.0;
+
+    let (Ok((mut transform, mut controller)), Ok(orbit_transform)) = (query.get_single_mut(), camera_orbit_query.get_single()) else {
+        return;
+    };
+
+    // full camera forward, so looking up and walking forward climbs
+    let forward = *orbit_transform.forward();
+    let right = *orbit_transform.right();
+    let mut direction = Vec3::ZERO;
+    if input.pressed(KeyCode::KeyW) {
+        direction += forward;
+    }
+    if input.pressed(KeyCode::KeyS) {
+        direction -= forward;
+    }
+    if input.pressed(KeyCode::KeyD) {
+        direction += right;
+    }
+    if input.pressed(KeyCode::KeyA) {
+        direction -= right;
+    }
+    if input.pressed(KeyCode::Space) {
+        direction += Vec3::Y;
+    }
+    if input.pressed(KeyCode::ControlLeft) {
+        direction -= Vec3::Y;
+    }
+
+    let speed = if input.pressed(KeyCode::ShiftLeft) { FLY * FAST_MULTIPLIER } else { FLY };
+    // the body is moved directly, the character controller would stop at walls
+    controller.translation = None;
+    transform.translation += direction.normalize_or_zero() * speed * time.delta_secs();
+}
+
+fn touching_climbable(
+    context: &RapierContext,
+    player_entity: Entity,
+    transform: &Transform,
+    collider: &Collider,
+    climbable_query: &Query<(&Transform, &Collider), (With<Climbable>, Without<Player>)>,
+) -> Option<Entity> {
+    let mut found = None;
+    context.intersections_with_shape(
+        transform.translation,
+        transform.rotation,
+        collider,
+        QueryFilter::default().exclude_collider(player_entity),
+        |entity| {
+            if climbable_query.contains(entity) {
+                found = Some(entity);
+                return false;
+            }
+            true
+        },
+    );
+    found
+}
+
+fn horizontal_direction(from: Vec3, to: Vec3) -> Vec3 {
+    Vec3::new(to.x - from.x, 0.0, to.z - from.z).normalize_or_zero()
+}
+
 fn handle_interaction(
-    query: Query<(Entity, &Transform), With<Player>>,
-    check_query: Query<(&KinematicCharacterControllerOutput, &GameState), (With<Player>, With<GameStateMarker>)>,
-    interactable_query: Query<Entity, With<Interactable>>,
+    query: Query<(Entity, &Transform, &KinematicCharacterControllerOutput), With<Player>>,
+    game_query: Query<&GameState, With<GameStateMarker>>,
+    interactable_query: Query<&GlobalTransform, With<Interactable>>,
     rapier_context: Query<&RapierContext>, // REMEMBER ITS A COMPONENT, NOT A RESOURCE
     dialog_query: Query<Entity, With<DialogTrigger>>,
     door_query: Query<Entity, With<DoorTrigger>>,
     item_query: Query<Entity, With<ItemTrigger>>,
     input: Res<ButtonInput<KeyCode>>,
+    mut focus: ResMut<InteractionFocus>,
     mut event_writer: EventWriter<InteractType>,
 ) {
-    if let Ok((output, state)) = check_query.get_single() {
-        if !output.grounded || state.paused || state.in_dialog { return; }
-    }
+    focus.in_range.clear();
+    focus.target = None;
 
-    let (player_entity, transform) = query.single();
-    const INT_RADIUS: f32 = 4.5;
+    // no player between rooms or in a bare app, and nothing to interact with while a menu or dialog is up
+    let (Ok((player_entity, transform, output)), Ok(state)) = (query.get_single(), game_query.get_single()) else {
+        return;
+    };
+    if !output.grounded || !state.in_gameplay() { return; }
 
     if let Ok(context) = rapier_context.get_single() {
         context.intersections_with_shape(
@@ -293,20 +545,33 @@ fn handle_interaction(
             |entity| {
                 // if the found entity isn't ourselves AND it is an entity marked as interactable
                 if entity != player_entity && interactable_query.contains(entity) {
-                    if input.just_pressed(KeyCode::KeyE) {
-                        if dialog_query.contains(entity) {
-                            event_writer.send(InteractType::Dialog(entity));
-                        }
-                        if door_query.contains(entity) {
-                            event_writer.send(InteractType::Door(entity));
-                        }
-                        if item_query.contains(entity) {
-                            event_writer.send(InteractType::Item(entity));
-                        }
-                    }
+                    focus.in_range.push(entity);
                 }
                 true
             },
         );
     }
-}
\ No newline at end of file
+
+    focus.target = focus
+        .in_range
+        .iter()
+        .filter_map(|entity| Some((*entity, interactable_query.get(*entity).ok()?.translation())))
+        .min_by(|(_, a), (_, b)| {
+            a.distance_squared(transform.translation).total_cmp(&b.distance_squared(transform.translation))
+        })
+        .map(|(entity, _)| entity);
+
+    if input.just_pressed(KeyCode::KeyE) {
+        for entity in focus.in_range.iter().copied() {
+            if dialog_query.contains(entity) {
+                event_writer.send(InteractType::Dialog(entity));
+            }
+            if door_query.contains(entity) {
+                event_writer.send(InteractType::Door(entity));
+            }
+            if item_query.contains(entity) {
+                event_writer.send(InteractType::Item(entity));
+            }
+        }
+    }
+}
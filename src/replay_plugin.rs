@@ -0,0 +1,535 @@
+use std::hash::Hash;
+use std::time::Duration;
+
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::time::{TimeSystem, TimeUpdateStrategy};
+use bevy_rapier3d::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::camera_plugin::CameraOrbit;
+use crate::mechanics::flags::StoryFlags;
+use crate::mechanics::inventory::Inventory;
+use crate::mechanics::quest::QuestLog;
+use crate::mechanics::room::{CurrentRoom, LoadRoom};
+use crate::mechanics::save::SaveData;
+use crate::player_plugin::{player_collider, MovementMode, Player, PlayerPhysics, CROUCHING_HEIGHT, STANDING_HEIGHT};
+use crate::settings_plugin::Settings;
+
+// records input per tick and plays it back, for reproducing bugs and as regression tests
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Replay>()
+            .add_event::<ReplayEvent>()
+            // starts and stops on a frame boundary, before the frame's delta is picked
+            .add_systems(First, handle_replay_events.before(TimeSystem))
+            .add_systems(PreUpdate, (play_input, record_input).chain().after(InputSystem));
+    }
+}
+
+pub fn replay_path(name: &str) -> String {
+    format!("replays/{}.replay.ron", name)
+}
+
+#[derive(Event, Clone)]
+pub enum ReplayEvent {
+    Record,
+    StopRecording(Option<String>), // writes the recording to this path, it's kept in Replay either way
+    Play(Recording),
+    StopPlayback,
+}
+
+// a starting state plus the input of every tick after it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Recording {
+    pub save: SaveData,
+    pub settings: Settings, // camera sensitivity and smoothing shape the trajectory too
+    pub start: ReplaySnapshot,
+    pub end: ReplaySnapshot, // where the recording stopped, playback reports how far off it ends up
+    pub frames: Vec<InputFrame>,
+}
+
+impl Recording {
+    pub fn read(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        ron::from_str(&contents).map_err(|err| err.to_string())
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let contents = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        std::fs::write(path, contents).map_err(|err| err.to_string())
+    }
+}
+
+// player and camera state that carries over between frames
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplaySnapshot {
+    pub translation: Vec3,
+    pub physics: PlayerPhysics,
+    pub grounded: bool, // last controller output, the next frame's movement reads it
+    pub orbit: CameraOrbit,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct InputFrame {
+    pub delta: f32, // length of the tick, playback steps time by exactly this so physics sees the same deltas
+    pub keys: Buttons<KeyCode>,
+    pub mouse_buttons: Buttons<MouseButton>,
+    pub mouse_motion: Vec2, // summed over the tick
+    pub scroll: f32, // in lines
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Buttons<T> {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pressed: Vec<T>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub just_pressed: Vec<T>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub just_released: Vec<T>,
+}
+
+impl<T> Default for Buttons<T> {
+    fn default() -> Self {
+        Self {
+            pressed: Vec::new(),
+            just_pressed: Vec::new(),
+            just_released: Vec::new(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash + Send + Sync + 'static> Buttons<T> {
+    fn read(input: &ButtonInput<T>) -> Self {
+        Self {
+            pressed: input.get_pressed().copied().collect(),
+            just_pressed: input.get_just_pressed().copied().collect(),
+            just_released: input.get_just_released().copied().collect(),
+        }
+    }
+
+    // rebuilds the exact state, including buttons tapped and let go within the same tick
+    fn apply(&self, input: &mut ButtonInput<T>) {
+        input.reset_all();
+        for button in &self.just_released {
+            input.press(*button);
+            input.release(*button);
+        }
+        for button in &self.pressed {
+            input.press(*button);
+        }
+        for button in self.pressed.iter().chain(&self.just_released) {
+            if !self.just_pressed.contains(button) {
+                input.clear_just_pressed(*button);
+            }
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Replay {
+    state: ReplayState,
+    last_recording: Option<Recording>,
+    previous_time: Option<TimeUpdateStrategy>, // put back once playback ends
+    previous_settings: Option<Settings>,
+    previous_save: Option<SaveData>, // progress from before the replay, the recording's save replaces it
+    previous_snapshot: Option<ReplaySnapshot>, // where the live session was, put back after the replay
+}
+
+#[derive(Default)]
+enum ReplayState {
+    #[default]
+    Idle,
+    Recording(Recording),
+    Loading { recording: Recording, started: bool }, // waiting for the recorded room before the first frame
+    Playing { recording: Recording, frame: usize },
+    Returning { save: SaveData, started: bool }, // reloading the room the replay started from
+}
+
+// what the room loading watched from handle_replay_events allows next
+enum ReplayStep {
+    Wait,
+    Begin,
+    Fail,
+    Finish,
+    Return,
+}
+
+impl Replay {
+    pub fn is_recording(&self) -> bool {
+        matches!(self.state, ReplayState::Recording(_))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.state, ReplayState::Loading { .. } | ReplayState::Playing { .. })
+    }
+
+    // frames played and the total, while playing
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        match &self.state {
+            ReplayState::Playing { recording, frame } => Some((*frame, recording.frames.len())),
+            _ => None,
+        }
+    }
+
+    pub fn last_recording(&self) -> Option<&Recording> {
+        self.last_recording.as_ref()
+    }
+}
+
+// exclusive so it can snapshot and restore whatever the app has, the headless tests run without rooms or a save
+fn handle_replay_events(world: &mut World) {
+    let events: Vec<ReplayEvent> = world.resource_mut::<Events<ReplayEvent>>().drain().collect();
+
+    for event in events {
+        let idle = matches!(world.resource::<Replay>().state, ReplayState::Idle);
+        match event {
+            ReplayEvent::Record => {
+                if !idle {
+                    warn!("Can't record, already recording or replaying");
+                    continue;
+                }
+                let Some(start) = snapshot(world) else {
+                    warn!("Can't record without a player and camera");
+                    continue;
+                };
+                let recording = Recording {
+                    save: current_save(world),
+                    settings: world.resource::<Settings>().clone(),
+                    end: start.clone(),
+                    start,
+                    frames: Vec::new(),
+                };
+                world.resource_mut::<Replay>().state = ReplayState::Recording(recording);
+                info!("Recording input");
+            }
+            ReplayEvent::StopRecording(path) => {
+                if !world.resource::<Replay>().is_recording() {
+                    warn!("Not recording");
+                    continue;
+                }
+                let ReplayState::Recording(mut recording) = std::mem::take(&mut world.resource_mut::<Replay>().state) else {
+                    continue;
+                };
+                if let Some(end) = snapshot(world) {
+                    recording.end = end;
+                }
+                info!("Recorded {} frames", recording.frames.len());
+                if let Some(path) = path {
+                    match recording.write(&path) {
+                        Ok(()) => info!("Recording written to {}", path),
+                        Err(err) => warn!("Failed to write recording: {}", err),
+                    }
+                }
+                world.resource_mut::<Replay>().last_recording = Some(recording);
+            }
+            ReplayEvent::Play(recording) => {
+                if !idle {
+                    warn!("Can't replay, already recording or replaying");
+                    continue;
+                }
+                let previous = current_save(world);
+                world.resource_mut::<Replay>().previous_save = Some(previous);
+                let previous = snapshot(world);
+                world.resource_mut::<Replay>().previous_snapshot = previous;
+                apply_save(world, &recording.save);
+                let previous = std::mem::replace(&mut *world.resource_mut::<Settings>(), recording.settings.clone());
+                world.resource_mut::<Replay>().previous_settings = Some(previous);
+
+                // reloading the room also resets anything the recording's start didn't capture, like platforms
+                let loads_rooms = world.contains_resource::<Events<LoadRoom>>();
+                match recording.save.room.clone() {
+                    Some(room) if loads_rooms => {
+                        world.send_event(LoadRoom {
+                            room,
+                            spawn_point: recording.save.spawn_point.clone(),
+                        });
+                        world.resource_mut::<Replay>().state = ReplayState::Loading { recording, started: false };
+                    }
+                    _ => begin_playback(world, recording),
+                }
+            }
+            ReplayEvent::StopPlayback => {
+                if world.resource::<Replay>().is_playing() {
+                    finish_playback(world);
+                } else {
+                    warn!("Not replaying");
+                }
+            }
+        }
+    }
+
+    let busy = world
+        .get_resource::<CurrentRoom>()
+        .map(|current| (current.is_loading() || current.is_placing_player(), current.name.clone()));
+    let mut replay = world.resource_mut::<Replay>();
+    let step = match &mut replay.state {
+        // the room has to be in and the player placed, or place_player would undo the start state
+        ReplayState::Loading { recording, started } => match busy {
+            Some((true, _)) => {
+                *started = true;
+                ReplayStep::Wait
+            }
+            // once the load has gone through, a different room means the recorded one failed to load
+            Some((false, name)) if *started && name == recording.save.room => ReplayStep::Begin,
+            Some((false, _)) if *started => ReplayStep::Fail,
+            Some((false, _)) => ReplayStep::Wait,
+            None => ReplayStep::Begin,
+        },
+        ReplayState::Playing { recording, frame } if *frame >= recording.frames.len() => ReplayStep::Finish,
+        ReplayState::Returning { started, .. } => match busy {
+            Some((true, _)) => {
+                *started = true;
+                ReplayStep::Wait
+            }
+            Some((false, _)) if !*started => ReplayStep::Wait,
+            _ => ReplayStep::Return,
+        },
+        _ => ReplayStep::Wait,
+    };
+
+    match step {
+        ReplayStep::Wait => {}
+        ReplayStep::Begin => {
+            if let ReplayState::Loading { recording, .. } = std::mem::take(&mut world.resource_mut::<Replay>().state) {
+                begin_playback(world, recording);
+            }
+        }
+        ReplayStep::Fail => {
+            warn!("Can't replay, the recorded room failed to load");
+            finish_playback(world);
+        }
+        ReplayStep::Finish => finish_playback(world),
+        ReplayStep::Return => {
+            if let ReplayState::Returning { save, .. } = std::mem::take(&mut world.resource_mut::<Replay>().state) {
+                return_to_session(world, &save);
+            }
+        }
+    }
+
+    // the frame about to start runs for as long as the recorded one did
+    let delta = match &world.resource::<Replay>().state {
+        ReplayState::Playing { recording, frame } => recording.frames.get(*frame).map(|input| input.delta),
+        _ => None,
+    };
+    if let Some(delta) = delta {
+        set_manual_time(world, delta);
+    }
+}
+
+fn begin_playback(world: &mut World, recording: Recording) {
+    restore(world, &recording.start);
+    info!("Replaying {} frames", recording.frames.len());
+    world.resource_mut::<Replay>().state = ReplayState::Playing { recording, frame: 0 };
+}
+
+fn finish_playback(world: &mut World) {
+    let state = std::mem::take(&mut world.resource_mut::<Replay>().state);
+    restore_time(world);
+    if let Some(settings) = world.resource_mut::<Replay>().previous_settings.take() {
+        *world.resource_mut::<Settings>() = settings;
+    }
+    // nothing sends the releases for keys still held in the recording
+    world.resource_mut::<ButtonInput<KeyCode>>().reset_all();
+    world.resource_mut::<ButtonInput<MouseButton>>().reset_all();
+
+    match state {
+        ReplayState::Playing { recording, frame } if frame >= recording.frames.len() => {
+            let drift = snapshot(world).map_or(0.0, |end| end.translation.distance(recording.end.translation));
+            info!("Replay finished, player ended {:.3} from the recorded position", drift);
+        }
+        ReplayState::Playing { recording, frame } => {
+            info!("Replay stopped at frame {}/{}", frame, recording.frames.len());
+        }
+        _ => info!("Replay stopped"),
+    }
+
+    let Some(save) = world.resource_mut::<Replay>().previous_save.take() else {
+        return;
+    };
+    apply_save(world, &save);
+    // the replay may have moved to another room, and reloaded ours either way
+    let loads_rooms = world.contains_resource::<Events<LoadRoom>>();
+    match save.room.clone() {
+        Some(room) if loads_rooms => {
+            world.send_event(LoadRoom {
+                room,
+                spawn_point: save.spawn_point.clone(),
+            });
+            world.resource_mut::<Replay>().state = ReplayState::Returning { save, started: false };
+        }
+        _ => return_to_session(world, &save),
+    }
+}
+
+// progress, position and camera as they were before the replay started
+fn return_to_session(world: &mut World, save: &SaveData) {
+    // after the room load, whose RoomChanged would otherwise overwrite the saved rooms
+    apply_save(world, save);
+    if let Some(snapshot) = world.resource_mut::<Replay>().previous_snapshot.take() {
+        restore(world, &snapshot);
+    }
+}
+
+fn snapshot(world: &mut World) -> Option<ReplaySnapshot> {
+    let (translation, physics, grounded) = {
+        let mut player_query = world.query_filtered::<
+            (&Transform, &PlayerPhysics, &KinematicCharacterControllerOutput),
+            With<Player>,
+        >();
+        let (transform, physics, output) = player_query.get_single(world).ok()?;
+        (transform.translation, physics.clone(), output.grounded)
+    };
+    let orbit = world.query::<&CameraOrbit>().get_single(world).ok()?.clone();
+
+    Some(ReplaySnapshot {
+        translation,
+        physics,
+        grounded,
+        orbit,
+    })
+}
+
+fn restore(world: &mut World, snapshot: &ReplaySnapshot) {
+    let mut player_query = world.query_filtered::<(
+        Entity,
+        &mut Transform,
+        &mut PlayerPhysics,
+        &mut KinematicCharacterControllerOutput,
+        &mut MovementMode,
+    ), With<Player>>();
+    if let Ok((entity, mut transform, mut physics, mut output, mut mode)) = player_query.get_single_mut(world) {
+        transform.translation = snapshot.translation;
+        *physics = snapshot.physics.clone();
+        output.grounded = snapshot.grounded;
+        *mode = MovementMode::Walking;
+
+        // the capsule has to match the stance the recording started in
+        let height = if snapshot.physics.crouching { CROUCHING_HEIGHT } else { STANDING_HEIGHT };
+        world.entity_mut(entity).insert(player_collider(height));
+    }
+
+    if let Ok(mut orbit) = world.query::<&mut CameraOrbit>().get_single_mut(world) {
+        *orbit = snapshot.orbit.clone();
+    }
+}
+
+// same as a save, with whatever progress the app keeps copied in
+fn current_save(world: &World) -> SaveData {
+    let mut save = world.get_resource::<SaveData>().cloned().unwrap_or_default();
+    if let Some(flags) = world.get_resource::<StoryFlags>() {
+        save.flags = flags.0.clone();
+    }
+    if let Some(inventory) = world.get_resource::<Inventory>() {
+        save.inventory = inventory.clone();
+    }
+    if let Some(quests) = world.get_resource::<QuestLog>() {
+        save.quests = quests.clone();
+    }
+    save
+}
+
+fn apply_save(world: &mut World, save: &SaveData) {
+    if let Some(mut flags) = world.get_resource_mut::<StoryFlags>() {
+        flags.0 = save.flags.clone();
+    }
+    if let Some(mut inventory) = world.get_resource_mut::<Inventory>() {
+        *inventory = save.inventory.clone();
+    }
+    if let Some(mut quests) = world.get_resource_mut::<QuestLog>() {
+        *quests = save.quests.clone();
+    }
+    if let Some(mut save_data) = world.get_resource_mut::<SaveData>() {
+        *save_data = save.clone();
+    }
+}
+
+fn set_manual_time(world: &mut World, delta: f32) {
+    let manual = TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(delta));
+    let previous = std::mem::replace(&mut *world.resource_mut::<TimeUpdateStrategy>(), manual);
+    let mut replay = world.resource_mut::<Replay>();
+    // only the strategy from before playback is kept, not the previous frame's delta
+    if replay.previous_time.is_none() {
+        replay.previous_time = Some(previous);
+    }
+}
+
+fn restore_time(world: &mut World) {
+    if let Some(previous) = world.resource_mut::<Replay>().previous_time.take() {
+        *world.resource_mut::<TimeUpdateStrategy>() = previous;
+    }
+}
+
+// overwrites this tick's input with the recorded one, real input is dropped while replaying
+fn play_input(
+    mut replay: ResMut<Replay>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut mouse_buttons: ResMut<ButtonInput<MouseButton>>,
+    mut mouse_motion: ResMut<Events<MouseMotion>>,
+    mut mouse_wheel: ResMut<Events<MouseWheel>>,
+) {
+    let ReplayState::Playing { recording, frame } = &mut replay.state else {
+        return;
+    };
+    let Some(input) = recording.frames.get(*frame) else {
+        return;
+    };
+
+    input.keys.apply(&mut keys);
+    input.mouse_buttons.apply(&mut mouse_buttons);
+    mouse_motion.clear();
+    if input.mouse_motion != Vec2::ZERO {
+        mouse_motion.send(MouseMotion { delta: input.mouse_motion });
+    }
+    mouse_wheel.clear();
+    if input.scroll != 0.0 {
+        mouse_wheel.send(MouseWheel {
+            unit: MouseScrollUnit::Line,
+            x: 0.0,
+            y: input.scroll,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+    *frame += 1;
+}
+
+fn record_input(
+    mut replay: ResMut<Replay>,
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+) {
+    const PIXELS_PER_LINE: f32 = 100.0;
+
+    let ReplayState::Recording(recording) = &mut replay.state else {
+        mouse_motion.clear();
+        mouse_wheel.clear();
+        return;
+    };
+
+    recording.frames.push(InputFrame {
+        delta: time.delta_secs(),
+        keys: Buttons::read(&keys),
+        mouse_buttons: Buttons::read(&mouse_buttons),
+        mouse_motion: mouse_motion.read().map(|event| event.delta).sum(),
+        scroll: mouse_wheel
+            .read()
+            .map(|event| match event.unit {
+                MouseScrollUnit::Line => event.y,
+                MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+            })
+            .sum(),
+    });
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Settings::load())
+            .add_event::<SaveSettings>()
+            .add_systems(Update, save_settings);
+    }
+}
+
+const SETTINGS_PATH: &str = "settings.ron";
+
+// user preferences, persisted as RON in the working directory
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub camera: CameraSettings,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub sensitivity: f32,
+    pub invert_y: bool,
+    pub smoothing: f32, // 0.0 is raw input, closer to 1.0 is heavier smoothing
+    pub mode: CameraMode,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: 1.0,
+            invert_y: false,
+            smoothing: 0.0,
+            mode: CameraMode::ThirdPerson,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CameraMode {
+    ThirdPerson,
+    FirstPerson,
+}
+
+impl Settings {
+    fn load() -> Self {
+        match std::fs::read_to_string(SETTINGS_PATH) {
+            Ok(contents) => ron::from_str(&contents).unwrap_or_else(|err| {
+                warn!("Failed to parse {}, using defaults: {}", SETTINGS_PATH, err);
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let contents = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        std::fs::write(SETTINGS_PATH, contents).map_err(|err| err.to_string())
+    }
+}
+
+#[derive(Event, Clone)]
+pub struct SaveSettings;
+
+// settings edited in a menu, applied by GuiPlugin so the menu itself only reads them
+#[derive(Event, Clone)]
+pub struct ChangeSettings(pub Settings);
+
+fn save_settings(
+    mut event_reader: EventReader<SaveSettings>,
+    settings: Res<Settings>,
+) {
+    if event_reader.read().last().is_some() {
+        if let Err(err) = settings.save() {
+            warn!("Failed to save settings: {}", err);
+        }
+    }
+}
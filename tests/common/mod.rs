@@ -0,0 +1,204 @@
+// headless app for gameplay tests: physics and game logic, no window, renderer or egui
+#![allow(dead_code)] // each test binary only uses part of the harness
+
+use std::time::Duration;
+
+use bevy::input::mouse::{MouseMotion, MouseWheel};
+use bevy::prelude::*;
+use bevy::render::mesh::MeshPlugin;
+use bevy::scene::ScenePlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy_rapier3d::prelude::*;
+
+use room_world::camera_plugin::{CameraOrbit, CameraPlugin};
+use room_world::gui_plugin::{GameState, GuiPlugin};
+use room_world::mechanics::dialog::{DialogAsset, DialogData, DialogEntity, DialogPlugin};
+use room_world::mechanics::navigation::NavigationPlugin;
+use room_world::mechanics::platform::PlatformPlugin;
+use room_world::mechanics::room::RoomChanged;
+use room_world::player_plugin::{DialogTrigger, Interactable, Player, PlayerPhysics, PlayerPlugin};
+#[cfg(feature = "debug")]
+use room_world::replay_plugin::{Replay, ReplayPlugin};
+use room_world::settings_plugin::{SaveSettings, Settings};
+
+pub const FRAME_TIME: f32 = 1.0 / 60.0;
+
+pub struct TestApp {
+    pub app: App,
+}
+
+impl TestApp {
+    // player standing on a flat floor, camera looking down -Z so W walks towards -Z
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            HierarchyPlugin,
+            AssetPlugin::default(),
+            MeshPlugin,
+            ScenePlugin,
+        ))
+        .init_asset::<StandardMaterial>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(FRAME_TIME)))
+        // input is driven by hand, InputPlugin would clear it before our systems saw it
+        .init_resource::<ButtonInput<KeyCode>>()
+        .init_resource::<ButtonInput<MouseButton>>()
+        .add_event::<MouseMotion>()
+        .add_event::<MouseWheel>()
+        // default settings, SettingsPlugin would read and write settings.ron
+        .init_resource::<Settings>()
+        .add_event::<SaveSettings>()
+        // no rooms, the navmesh bakes from the test level once its colliders are in
+        .add_event::<RoomChanged>()
+        .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+        .add_plugins((GuiPlugin, PlayerPlugin, CameraPlugin, DialogPlugin, NavigationPlugin, PlatformPlugin))
+        .add_systems(Startup, spawn_test_level);
+
+        // recording and replaying is debug tooling, like the console that drives it
+        #[cfg(feature = "debug")]
+        app.add_plugins(ReplayPlugin);
+
+        app.finish();
+        app.cleanup();
+        Self { app }
+    }
+
+    pub fn press(&mut self, key: KeyCode) {
+        self.app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(key);
+    }
+
+    pub fn release(&mut self, key: KeyCode) {
+        self.app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release(key);
+    }
+
+    // mouse look, seen on the next step
+    pub fn move_mouse(&mut self, delta: Vec2) {
+        self.app.world_mut().send_event(MouseMotion { delta });
+    }
+
+    // press for a single frame
+    pub fn tap(&mut self, key: KeyCode) {
+        self.press(key);
+        self.step(1);
+        self.release(key);
+    }
+
+    pub fn step(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.app.update();
+            // held keys stay pressed, just_pressed only lasts the frame it happened in
+            self.app.world_mut().resource_mut::<ButtonInput<KeyCode>>().clear();
+            self.app.world_mut().resource_mut::<ButtonInput<MouseButton>>().clear();
+        }
+    }
+
+    pub fn game_state(&mut self) -> &GameState {
+        self.app
+            .world_mut()
+            .query::<&GameState>()
+            .single(self.app.world())
+    }
+
+    pub fn player_physics(&mut self) -> &PlayerPhysics {
+        self.app
+            .world_mut()
+            .query_filtered::<&PlayerPhysics, With<Player>>()
+            .single(self.app.world())
+    }
+
+    pub fn player_transform(&mut self) -> Transform {
+        *self
+            .app
+            .world_mut()
+            .query_filtered::<&Transform, With<Player>>()
+            .single(self.app.world())
+    }
+
+    pub fn player_grounded(&mut self) -> bool {
+        self.app
+            .world_mut()
+            .query_filtered::<&KinematicCharacterControllerOutput, With<Player>>()
+            .single(self.app.world())
+            .grounded
+    }
+
+    pub fn orbit(&mut self) -> CameraOrbit {
+        self.app
+            .world_mut()
+            .query::<&CameraOrbit>()
+            .single(self.app.world())
+            .clone()
+    }
+
+    #[cfg(feature = "debug")]
+    pub fn replay(&self) -> &Replay {
+        self.app.world().resource::<Replay>()
+    }
+
+    // send an event as if some system or UI had, it's seen on the next step
+    pub fn send<E: Event>(&mut self, event: E) {
+        self.app.world_mut().send_event(event);
+    }
+
+    // start recording events of this type, read them back with `events`
+    pub fn collect<E: Event + Clone>(&mut self) {
+        self.app
+            .init_resource::<Collected<E>>()
+            .add_systems(Last, collect_events::<E>);
+    }
+
+    pub fn events<E: Event + Clone>(&self) -> Vec<E> {
+        self.app.world().resource::<Collected<E>>().0.clone()
+    }
+
+    // talkable entity next to the player, within interaction range
+    pub fn spawn_speaker(&mut self, lines: &[&str], position: Vec3) -> Entity {
+        let dialog = DialogAsset {
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+            shots: Vec::new(),
+        };
+        let handle = self
+            .app
+            .world_mut()
+            .resource_mut::<Assets<DialogAsset>>()
+            .add(dialog);
+
+        self.app
+            .world_mut()
+            .spawn((
+                Name::new("Tester"),
+                Collider::ball(0.5),
+                Transform::from_translation(position),
+                Interactable,
+                DialogTrigger,
+                DialogEntity,
+                DialogData { dialog_file: handle },
+            ))
+            .id()
+    }
+}
+
+#[derive(Resource)]
+struct Collected<E>(Vec<E>);
+
+impl<E> Default for Collected<E> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+fn collect_events<E: Event + Clone>(
+    mut event_reader: EventReader<E>,
+    mut collected: ResMut<Collected<E>>,
+) {
+    collected.0.extend(event_reader.read().cloned());
+}
+
+fn spawn_test_level(mut commands: Commands) {
+    commands.spawn((
+        RigidBody::Fixed,
+        Collider::cuboid(50.0, 0.1, 50.0),
+        Transform::from_xyz(0.0, -0.1, 0.0),
+    ));
+}
@@ -0,0 +1,348 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use common::TestApp;
+use room_world::gui_plugin::{GameEvent, GameState};
+use room_world::mechanics::dialog::{DialogAsset, DialogEvent};
+use room_world::mechanics::platform::{Elevator, PlatformVelocity};
+use room_world::player_plugin::{InteractType, InteractionFocus};
+use room_world::settings_plugin::{ChangeSettings, SaveSettings, Settings};
+
+// long enough for the player to drop onto the floor and the grounded timer to fill
+const SETTLE_FRAMES: usize = 45;
+
+fn settled_app() -> TestApp {
+    let mut test = TestApp::new();
+    test.step(SETTLE_FRAMES);
+    assert!(test.player_grounded(), "player should land on the floor");
+    test
+}
+
+#[test]
+fn walking_forward_moves_player() {
+    let mut test = settled_app();
+    let start = test.player_transform().translation;
+
+    test.press(KeyCode::KeyW);
+    test.step(60);
+
+    let end = test.player_transform().translation;
+    assert!(start.z - end.z > 2.0, "expected to walk towards -Z, moved from {start} to {end}");
+    assert!((end.x - start.x).abs() < 0.1);
+    assert!(test.player_physics().velocity.z < 0.0);
+
+    // friction brings the player to a stop once the key is let go
+    test.release(KeyCode::KeyW);
+    test.step(60);
+    assert_eq!(test.player_physics().velocity, Vec3::ZERO);
+}
+
+#[test]
+fn jumping_leaves_the_ground_and_lands() {
+    let mut test = settled_app();
+    let rest_height = test.player_transform().translation.y;
+
+    test.tap(KeyCode::Space);
+    test.step(15);
+
+    assert!(test.player_transform().translation.y - rest_height > 0.5);
+    assert!(!test.player_grounded());
+
+    test.step(120);
+    assert!(test.player_grounded());
+    assert!((test.player_transform().translation.y - rest_height).abs() < 0.05);
+}
+
+#[test]
+fn crouching_keeps_the_camera_out_of_the_floor() {
+    let mut test = settled_app();
+    assert!(test.orbit().boom > 0.95);
+
+    test.press(KeyCode::ControlLeft);
+    test.step(60);
+    assert!(test.player_physics().crouching);
+    let orbit = test.orbit();
+    assert!(orbit.boom > 0.95, "boom collapsed to {} while crouching on open ground", orbit.boom);
+    assert!(orbit.height > 0.0);
+}
+
+#[test]
+fn jumping_off_a_moving_elevator_keeps_its_momentum() {
+    let mut test = TestApp::new();
+    test.app.world_mut().spawn((
+        RigidBody::KinematicPositionBased,
+        Collider::cuboid(2.0, 0.15, 2.0),
+        Transform::from_xyz(0.0, 0.15, 0.0),
+        Elevator::new(Vec3::new(0.0, 0.15, 0.0), Vec3::new(40.0, 0.15, 0.0), 3.0, 0.0),
+        PlatformVelocity::default(),
+    ));
+    test.step(SETTLE_FRAMES);
+    assert!(test.player_grounded(), "player should ride the elevator");
+    assert!(test.player_transform().translation.x > 1.0, "elevator didn't carry the player");
+
+    test.tap(KeyCode::Space);
+    test.step(2);
+    assert!(!test.player_grounded());
+    let takeoff = test.player_transform().translation.x;
+
+    // a third of a second at the elevator's 3 m/s, without touching any keys
+    test.step(20);
+    assert!(!test.player_grounded());
+    let drift = test.player_transform().translation.x - takeoff;
+    assert!(drift > 0.8, "only drifted {} after leaving the elevator", drift);
+}
+
+#[test]
+fn pausing_freezes_the_player() {
+    let mut test = settled_app();
+
+    test.tap(KeyCode::Escape);
+    assert!(test.game_state().paused);
+    assert!(!test.game_state().in_gameplay());
+
+    let start = test.player_transform().translation;
+    test.press(KeyCode::KeyW);
+    test.step(30);
+    assert_eq!(test.player_transform().translation, start);
+
+    test.release(KeyCode::KeyW);
+    test.tap(KeyCode::Escape);
+    assert!(!test.game_state().paused);
+    assert!(test.game_state().in_gameplay());
+}
+
+#[test]
+fn dialog_plays_through_to_the_end() {
+    let mut test = settled_app();
+    test.collect::<InteractType>();
+    test.collect::<DialogEvent>();
+    let speaker = test.spawn_speaker(&["Hello.", "How are you?", "Goodbye."], Vec3::new(2.0, 1.0, 0.0));
+    test.step(2);
+
+    test.tap(KeyCode::KeyE);
+    test.step(2);
+
+    assert!(matches!(&test.events::<InteractType>()[..], [InteractType::Dialog(entity)] if *entity == speaker));
+    assert!(matches!(&test.events::<DialogEvent>()[..], [DialogEvent::DialogData(_, entity)] if *entity == speaker));
+    let state = test.game_state();
+    assert!(state.in_dialog);
+    assert_eq!(state.dialog_speaker, Some(speaker));
+    assert_eq!(state.current_dialog_line, 0);
+
+    // walking is locked while talking
+    let start = test.player_transform().translation;
+    test.press(KeyCode::KeyW);
+    test.step(10);
+    test.release(KeyCode::KeyW);
+    assert_eq!(test.player_transform().translation, start);
+
+    test.tap(KeyCode::Space);
+    assert_eq!(test.game_state().current_dialog_line, 1);
+    test.tap(KeyCode::Space);
+    assert_eq!(test.game_state().current_dialog_line, 2);
+    assert_eq!(
+        test.game_state().current_dialog.as_ref().map(|dialog| dialog.lines[2].as_str()),
+        Some("Goodbye.")
+    );
+
+    test.tap(KeyCode::Space);
+    let state = test.game_state();
+    assert!(!state.in_dialog);
+    assert!(state.current_dialog.is_none());
+    assert!(state.dialog_speaker.is_none());
+    assert!(state.in_gameplay());
+}
+
+#[test]
+fn interaction_focus_tracks_what_is_in_range() {
+    let mut test = settled_app();
+    test.collect::<InteractType>();
+    let far = test.spawn_speaker(&["Over here."], Vec3::new(-3.5, 1.0, 0.0));
+    let near = test.spawn_speaker(&["Right here."], Vec3::new(1.5, 1.0, 0.0));
+    test.spawn_speaker(&["Out of reach."], Vec3::new(12.0, 1.0, 0.0));
+    test.step(2);
+
+    let focus = test.app.world().resource::<InteractionFocus>();
+    assert_eq!(focus.in_range.len(), 2);
+    assert_eq!(focus.target, Some(near));
+
+    // E reaches everything in range, the focus is only what the debug gizmos mark
+    test.tap(KeyCode::KeyE);
+    test.step(2);
+    let mut reached: Vec<Entity> = test
+        .events::<InteractType>()
+        .iter()
+        .filter_map(|event| match event {
+            InteractType::Dialog(entity) => Some(*entity),
+            _ => None,
+        })
+        .collect();
+    reached.sort();
+    let mut expected = vec![far, near];
+    expected.sort();
+    assert_eq!(reached, expected);
+}
+
+#[test]
+fn interact_key_is_ignored_while_paused_or_talking() {
+    let mut test = settled_app();
+    test.collect::<InteractType>();
+    test.spawn_speaker(&["Hello.", "Goodbye."], Vec3::new(2.0, 1.0, 0.0));
+    test.step(2);
+
+    test.tap(KeyCode::Escape);
+    test.tap(KeyCode::KeyE);
+    test.step(2);
+    assert!(test.events::<InteractType>().is_empty());
+    assert!(!test.game_state().in_dialog);
+
+    test.tap(KeyCode::Escape);
+    test.tap(KeyCode::KeyE);
+    test.step(2);
+    assert_eq!(test.events::<InteractType>().len(), 1);
+    assert!(test.game_state().in_dialog);
+
+    // E mid-conversation doesn't start it over
+    test.tap(KeyCode::Space);
+    assert_eq!(test.game_state().current_dialog_line, 1);
+    test.tap(KeyCode::KeyE);
+    test.step(2);
+    assert_eq!(test.events::<InteractType>().len(), 1);
+    assert_eq!(test.game_state().current_dialog_line, 1);
+}
+
+#[test]
+fn intents_drive_the_game_without_input() {
+    let mut test = settled_app();
+    test.collect::<AppExit>();
+    let speaker = test.spawn_speaker(&["First.", "Second."], Vec3::new(2.0, 1.0, 0.0));
+    test.step(2);
+    test.send(DialogEvent::DialogData(
+        DialogAsset { lines: vec!["First.".into(), "Second.".into()], shots: Vec::new() },
+        speaker,
+    ));
+    test.step(1);
+    assert!(test.game_state().in_dialog);
+
+    test.send(GameEvent::AdvanceDialog);
+    test.step(1);
+    assert_eq!(test.game_state().current_dialog_line, 1);
+    test.send(GameEvent::AdvanceDialog);
+    test.step(1);
+    assert!(!test.game_state().in_dialog);
+
+    test.send(GameEvent::TogglePause);
+    test.step(1);
+    assert!(test.game_state().paused);
+
+    test.send(GameEvent::Quit);
+    test.step(1);
+    assert!(matches!(test.events::<AppExit>()[..], [AppExit::Success]));
+}
+
+#[test]
+fn leaving_settings_saves_them_either_way() {
+    let mut test = settled_app();
+    test.collect::<SaveSettings>();
+
+    // back button
+    test.tap(KeyCode::Escape);
+    test.send(GameEvent::ToggleSettings);
+    test.step(1);
+    test.send(GameEvent::ToggleSettings);
+    test.step(1);
+    assert_eq!(test.events::<SaveSettings>().len(), 1);
+
+    // escape straight out of the menu
+    test.send(GameEvent::ToggleSettings);
+    test.step(1);
+    test.tap(KeyCode::Escape);
+    assert_eq!(test.events::<SaveSettings>().len(), 2);
+    assert!(!test.game_state().paused && !test.game_state().settings_open);
+
+    // pausing and unpausing alone has nothing to save
+    test.tap(KeyCode::Escape);
+    test.tap(KeyCode::Escape);
+    assert_eq!(test.events::<SaveSettings>().len(), 2);
+}
+
+#[test]
+fn inventory_key_releases_control_like_the_journal() {
+    let mut test = settled_app();
+
+    test.tap(KeyCode::KeyI);
+    assert!(test.game_state().inventory_open);
+    assert!(!test.game_state().in_gameplay());
+    // one screen at a time
+    test.tap(KeyCode::KeyJ);
+    assert!(!test.game_state().journal_open);
+
+    test.tap(KeyCode::KeyI);
+    assert!(!test.game_state().inventory_open);
+    assert!(test.game_state().in_gameplay());
+}
+
+#[test]
+fn settings_change_through_an_intent() {
+    let mut test = settled_app();
+    let mut changed = Settings::default();
+    changed.camera.sensitivity = 2.5;
+    changed.camera.invert_y = true;
+
+    test.send(ChangeSettings(changed.clone()));
+    test.step(1);
+    assert_eq!(*test.app.world().resource::<Settings>(), changed);
+}
+
+#[test]
+fn journal_key_opens_only_from_gameplay() {
+    let mut test = settled_app();
+
+    test.tap(KeyCode::KeyJ);
+    assert!(test.game_state().journal_open);
+    assert!(!test.game_state().in_gameplay());
+    test.tap(KeyCode::KeyJ);
+    assert!(!test.game_state().journal_open);
+
+    test.tap(KeyCode::Escape);
+    test.tap(KeyCode::KeyJ);
+    assert!(!test.game_state().journal_open, "journal opened over the pause menu");
+    test.tap(KeyCode::Escape);
+
+    // typed into the console instead
+    test.app.world_mut().query::<&mut GameState>().single_mut(test.app.world_mut()).console_open = true;
+    test.tap(KeyCode::KeyJ);
+    assert!(!test.game_state().journal_open, "journal opened while typing in the console");
+}
+
+#[cfg(feature = "debug")]
+#[test]
+fn noclip_flies_through_the_floor_and_lands_after() {
+    use room_world::player_plugin::ToggleNoclip;
+
+    let mut test = settled_app();
+    let start = test.player_transform().translation;
+
+    test.send(ToggleNoclip);
+    test.press(KeyCode::ControlLeft);
+    test.step(30);
+    test.release(KeyCode::ControlLeft);
+    assert!(test.player_transform().translation.y < start.y - 2.0, "should sink through the floor");
+
+    test.press(KeyCode::Space);
+    test.step(60);
+    test.release(KeyCode::Space);
+    let flying = test.player_transform().translation;
+    assert!(flying.y > start.y + 2.0);
+
+    // hovering, no gravity while flying
+    test.step(30);
+    assert!(test.player_transform().translation.distance(flying) < 1e-4);
+
+    test.tap(KeyCode::KeyN);
+    test.step(120);
+    assert!(test.player_grounded());
+    assert!((test.player_transform().translation.y - start.y).abs() < 0.05);
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use common::TestApp;
+use room_world::mechanics::navigation::{NavAgent, NavMesh};
+
+// long enough for the navmesh bake delay to run out after the level spawns
+const BAKE_FRAMES: usize = 60;
+
+fn spawn_agent(test: &mut TestApp, position: Vec3) -> Entity {
+    test.app
+        .world_mut()
+        .spawn((
+            NavAgent::new(3.0),
+            RigidBody::KinematicPositionBased,
+            Collider::capsule_y(0.5, 0.4),
+            KinematicCharacterController {
+                snap_to_ground: Some(CharacterLength::Absolute(0.5)),
+                ..default()
+            },
+            Transform::from_translation(position),
+        ))
+        .id()
+}
+
+#[test]
+fn agent_walks_around_a_wall_to_its_destination() {
+    let mut test = TestApp::new();
+    // across the straight line between start and goal, open at both ends
+    test.app.world_mut().spawn((
+        RigidBody::Fixed,
+        Collider::cuboid(4.0, 1.5, 0.25),
+        Transform::from_xyz(8.0, 1.5, 0.0),
+    ));
+    let agent = spawn_agent(&mut test, Vec3::new(8.0, 1.0, 6.0));
+    test.step(BAKE_FRAMES);
+    assert!(test.app.world().resource::<NavMesh>().grid.is_some(), "navmesh never baked");
+
+    let goal = Vec3::new(8.0, 0.0, -6.0);
+    test.app.world_mut().get_mut::<NavAgent>(agent).unwrap().go_to(goal);
+
+    // 15 seconds is plenty for the ~20 m around the wall
+    let mut widest = 0.0f32;
+    for _ in 0..900 {
+        test.step(1);
+        let position = test.app.world().get::<Transform>(agent).unwrap().translation;
+        widest = widest.max((position.x - 8.0).abs());
+        if !test.app.world().get::<NavAgent>(agent).unwrap().is_moving() {
+            break;
+        }
+    }
+
+    let position = test.app.world().get::<Transform>(agent).unwrap().translation;
+    assert!(!test.app.world().get::<NavAgent>(agent).unwrap().is_moving(), "still walking at {}", position);
+    assert!(position.xz().distance(goal.xz()) < 0.2, "stopped at {}", position);
+    assert!(widest > 4.0, "never went around the wall");
+}
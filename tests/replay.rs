@@ -0,0 +1,91 @@
+// replays are debug tooling, cargo test --features debug
+#![cfg(feature = "debug")]
+
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestApp;
+use room_world::replay_plugin::{Recording, ReplayEvent};
+
+const SETTLE_FRAMES: usize = 45;
+
+// player position and look after a frame
+fn pose(test: &mut TestApp) -> (Vec3, f32, f32) {
+    let orbit = test.orbit();
+    (test.player_transform().translation, orbit.yaw, orbit.pitch)
+}
+
+// walks, looks around, jumps and strafes, returning the recording and the pose after every recorded frame
+fn record_session() -> (Recording, Vec<(Vec3, f32, f32)>) {
+    let mut test = TestApp::new();
+    test.step(SETTLE_FRAMES);
+    test.send(ReplayEvent::Record);
+
+    let mut trajectory = Vec::new();
+    test.press(KeyCode::KeyW);
+    for frame in 0..120 {
+        match frame {
+            30 => test.press(KeyCode::Space),
+            31 => test.release(KeyCode::Space),
+            80 => test.press(KeyCode::KeyD),
+            _ => {}
+        }
+        if frame < 60 {
+            test.move_mouse(Vec2::new(6.0, 2.0));
+        }
+        test.step(1);
+        trajectory.push(pose(&mut test));
+    }
+    test.release(KeyCode::KeyW);
+    test.release(KeyCode::KeyD);
+
+    test.send(ReplayEvent::StopRecording(None));
+    test.step(1);
+    assert!(!test.replay().is_recording());
+    let recording = test.replay().last_recording().cloned().expect("recording kept after stopping");
+    (recording, trajectory)
+}
+
+#[test]
+fn replay_reproduces_the_recorded_trajectory() {
+    let (recording, trajectory) = record_session();
+    assert_eq!(recording.frames.len(), trajectory.len());
+
+    // through the file format, the way a saved recording comes back
+    let contents = ron::to_string(&recording).unwrap();
+    let recording: Recording = ron::from_str(&contents).unwrap();
+
+    // somewhere else entirely, with a key held that the replay should override
+    let mut test = TestApp::new();
+    test.step(SETTLE_FRAMES);
+    test.press(KeyCode::KeyA);
+    test.move_mouse(Vec2::new(-40.0, 0.0));
+    test.step(30);
+
+    let before = pose(&mut test);
+    test.send(ReplayEvent::Play(recording));
+    let mut replayed = Vec::new();
+    test.step(1);
+    while test.replay().progress().is_some() {
+        replayed.push(pose(&mut test));
+        assert!(replayed.len() <= trajectory.len(), "replay ran past the recording");
+        test.step(1);
+    }
+
+    assert_eq!(replayed.len(), trajectory.len());
+    for (frame, (expected, actual)) in trajectory.iter().zip(&replayed).enumerate() {
+        let same_position = expected.0.distance(actual.0) < 1e-4;
+        let same_look = (expected.1 - actual.1).abs() < 1e-5 && (expected.2 - actual.2).abs() < 1e-5;
+        assert!(same_position && same_look, "frame {frame}: recorded {expected:?}, replayed {actual:?}");
+    }
+
+    // back where the live session was, give or take the frame since
+    let after = pose(&mut test);
+    assert!(after.0.distance(before.0) < 0.5, "left at {:?}, started from {:?}", after.0, before.0);
+    assert!((after.1 - before.1).abs() < 1e-3 && (after.2 - before.2).abs() < 1e-3);
+
+    // held keys from the recording are let go once it ends, and the player comes to a stop
+    test.step(60);
+    assert_eq!(test.player_physics().velocity, Vec3::ZERO);
+}